@@ -0,0 +1,62 @@
+//! Times `iterate_valid_chains` on random hands: `cargo bench --bench chains`.
+
+use blackjack::cards::*;
+use blackjack::rules::*;
+use blackjack::strategy::shared::{iterate_valid_chains, Enumeration};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+const HANDS_PER_SIZE : usize = 200;
+
+fn bench(label: &str, rules: Rules, decks: usize, hand_sizes: &[usize])
+{
+    let rules = Arc::new(rules);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    println!("{}:", label);
+
+    for &hand_size in hand_sizes {
+
+        let now = Instant::now();
+        let mut n_chains = 0;
+        let mut longest = 0;
+        let mut truncated = 0;
+
+        for _ in 0..HANDS_PER_SIZE {
+
+            let mut deck : Vec<Card> = rules.deck(decks).iter().collect();
+            deck.shuffle(&mut rng);
+
+            let table = TableState::new(rules.clone(), deck[0]);
+            let hand : CardSet = deck[1..=hand_size].iter().cloned().collect();
+
+            let searched = iterate_valid_chains(&table, &hand, &mut |chain: &[Card]| {
+                n_chains += 1;
+                longest = longest.max(chain.len());
+            });
+
+            if searched == Enumeration::Truncated { truncated += 1; }
+        }
+
+        println!("  {} card hands: {:.1} chains per hand, longest {}, {} truncated, {:?} per hand",
+            hand_size,
+            n_chains as f64 / HANDS_PER_SIZE as f64,
+            longest,
+            truncated,
+            now.elapsed() / HANDS_PER_SIZE as u32);
+    }
+}
+
+fn main()
+{
+    bench("One deck", Rules::default(), 1, &[10, 20, 25, 30]);
+
+    let mut jokers = Rules::default();
+    jokers.jokers_per_deck = 2;
+    bench("Two decks with jokers", jokers, 2, &[10, 20, 30]);
+}
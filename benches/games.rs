@@ -0,0 +1,35 @@
+//! Times the default table over 30,000 seeded games, one thread:
+//! `cargo bench --bench games`.
+//!
+//! Measured the same way, the default table took about 1.3s when the chain
+//! search gave up after a fixed number of permutations, 1.7s once it
+//! enumerated every distinct chain, and 0.85s once hands became `CardSet`
+//! masks: about twice as fast as full enumeration on `Vec<Card>`.
+
+use blackjack::game::*;
+use blackjack::rules::*;
+use blackjack::strategy::registry::*;
+
+use std::time::Instant;
+
+const NUM_GAMES : u64 = 30_000;
+
+fn main()
+{
+    let registry = Registry::standard();
+    let player_types : Vec<_> = ["computer_v1", "computer_v1", "computer_v2", "computer_v1"]
+        .iter()
+        .map(|s| registry.parse(s).unwrap())
+        .collect();
+
+    let now = Instant::now();
+    let mut turns = 0;
+
+    for seed in 0..NUM_GAMES {
+        let mut game = Game::with_seed(&player_types, Rules::default(), seed);
+        game.run();
+        turns += game.log.len();
+    }
+
+    println!("{} games finished in {:?}, {} turns", NUM_GAMES, now.elapsed(), turns);
+}
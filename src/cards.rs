@@ -7,6 +7,7 @@ pub enum Suit
     Spades
 }
 
+use std::convert::TryFrom;
 use std::fmt::Write;
 impl std::fmt::Debug for Suit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,10 +20,18 @@ impl std::fmt::Debug for Suit {
     }
 }
 
-#[derive(PartialEq, Clone, Copy, Hash, Eq)]
+#[derive(PartialEq, Clone, Copy, Hash, Eq, PartialOrd, Ord)]
 pub enum Rank
 {
-    Val(i32),
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
     Jack,
     Queen,
     King,
@@ -32,8 +41,15 @@ pub enum Rank
 impl std::fmt::Debug for Rank {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_char(match self {
-            Rank::Val(10) => 'X',
-            Rank::Val(i) => format!("{}",i).chars().nth(0).unwrap(),
+            Rank::Two => '2',
+            Rank::Three => '3',
+            Rank::Four => '4',
+            Rank::Five => '5',
+            Rank::Six => '6',
+            Rank::Seven => '7',
+            Rank::Eight => '8',
+            Rank::Nine => '9',
+            Rank::Ten => 'X',
             Rank::Jack => 'J',
             Rank::Queen => 'Q',
             Rank::King => 'K',
@@ -42,7 +58,7 @@ impl std::fmt::Debug for Rank {
     }
 }
 
-#[derive(PartialEq,Eq)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Colour
 {
     Red,
@@ -69,9 +85,29 @@ impl Suit {
         self.colour() == Colour::Red
     }
 
+    pub fn index(&self) -> usize
+    {
+        match self {
+            Suit::Spades => 0,
+            Suit::Clubs => 1,
+            Suit::Hearts => 2,
+            Suit::Diamonds => 3
+        }
+    }
+
+    /// The letter `TryFrom<char>` reads back.
+    pub fn code(&self) -> char
+    {
+        match self {
+            Suit::Spades => 'S',
+            Suit::Clubs => 'C',
+            Suit::Hearts => 'H',
+            Suit::Diamonds => 'D'
+        }
+    }
+
     pub fn all() -> [Suit;4]
     {
-        return
         [
             Suit::Spades,
             Suit::Clubs,
@@ -85,41 +121,34 @@ impl Rank {
 
     pub fn adjacent(x: Rank, y: Rank) -> bool
     {
-        match y {
-            Rank::Val(10) => x == Rank::Val(9) || x == Rank::Jack,
-            Rank::Val(2) => x == Rank::Val(3) || x == Rank::Ace,
-            Rank::Val(i) => x == Rank::Val(i-1) || x == Rank::Val(i+1),
-            Rank::Jack => x == Rank::Val(10) || x == Rank::Queen,
-            Rank::Queen => x == Rank::Jack || x == Rank::King,
-            Rank::King => x == Rank::Queen || x == Rank::Ace,
-            Rank::Ace => x == Rank::King || x == Rank::Val(2)
-        }
+        (x.index() + 1) % 13 == y.index() || (y.index() + 1) % 13 == x.index()
     }
 
     pub fn face_value(&self) -> i32
     {
         match self {
-            Rank::Val(v) => *v,
-            Rank::Jack => 10,
-            Rank::Queen => 10,
-            Rank::King => 10,
-            Rank::Ace => 10
+            Rank::Jack | Rank::Queen | Rank::King | Rank::Ace => 10,
+            r => r.index() as i32 + 2
         }
     }
 
+    pub fn index(&self) -> usize
+    {
+        *self as usize
+    }
+
     pub fn all() -> [Rank;13]
     {
-        return
         [
-            Rank::Val(2),
-            Rank::Val(3),
-            Rank::Val(4),
-            Rank::Val(5),
-            Rank::Val(6),
-            Rank::Val(7),
-            Rank::Val(8),
-            Rank::Val(9),
-            Rank::Val(10),
+            Rank::Two,
+            Rank::Three,
+            Rank::Four,
+            Rank::Five,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Eight,
+            Rank::Nine,
+            Rank::Ten,
             Rank::Jack,
             Rank::Queen,
             Rank::King,
@@ -128,36 +157,570 @@ impl Rank {
     }
 }
 
-#[derive(PartialEq, Clone, Copy, Hash, Eq)]
-pub struct Card {
-    pub rank: Rank, 
-    pub suit: Suit
+/// Numeric rank, counting jack, queen, king and ace as 11 to 14.
+impl TryFrom<u8> for Rank {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Rank, String>
+    {
+        match value {
+            2..=14 => Ok(Rank::all()[value as usize - 2]),
+            _ => Err(format!("{} is not a valid rank", value))
+        }
+    }
 }
 
+impl TryFrom<char> for Rank {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Rank, String>
+    {
+        match c.to_ascii_uppercase() {
+            'A' => Ok(Rank::Ace),
+            'K' => Ok(Rank::King),
+            'Q' => Ok(Rank::Queen),
+            'J' => Ok(Rank::Jack),
+            'X' | 'T' => Ok(Rank::Ten),
+            d @ '2'..='9' => Rank::try_from(d as u8 - b'0'),
+            _ => Err(format!("'{}' is not a valid rank", c))
+        }
+    }
+}
+
+impl TryFrom<char> for Suit {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Suit, String>
+    {
+        match c.to_ascii_uppercase() {
+            'S' | '♠' => Ok(Suit::Spades),
+            'C' | '♣' => Ok(Suit::Clubs),
+            'H' | '♥' => Ok(Suit::Hearts),
+            'D' | '♦' => Ok(Suit::Diamonds),
+            _ => Err(format!("'{}' is not a valid suit", c))
+        }
+    }
+}
+
+/// Distinct cards: the 52 of a standard deck, then the red and black jokers.
+pub const CARD_TYPES : usize = 54;
+
+/// A card from a single deck, stored as its `index`: suit-major, so cards
+/// order by suit (♠ ♣ ♥ ♦) and then by rank, with the jokers last.
+#[derive(PartialEq, Clone, Copy, Hash, Eq, PartialOrd, Ord)]
+pub struct Card(u8);
+
 impl Card {
 
     pub fn new(rank: Rank, suit: Suit) -> Card
     {
-        Card {
-            rank,
-            suit
+        Card((suit.index() * 13 + rank.index()) as u8)
+    }
+
+    pub fn joker(colour: Colour) -> Card
+    {
+        match colour {
+            Colour::Red => Card(52),
+            Colour::Black => Card(53)
+        }
+    }
+
+    pub fn is_joker(&self) -> bool
+    {
+        self.0 >= 52
+    }
+
+    /// Panics for a joker, which has no rank.
+    pub fn rank(&self) -> Rank
+    {
+        if self.is_joker() { panic!("A joker has no rank"); }
+        Rank::all()[self.0 as usize % 13]
+    }
+
+    /// Panics for a joker, which has no suit.
+    pub fn suit(&self) -> Suit
+    {
+        if self.is_joker() { panic!("A joker has no suit"); }
+        Suit::all()[self.0 as usize / 13]
+    }
+
+    /// Whether this is a card of `rank`; never true for a joker.
+    pub fn has_rank(&self, rank: Rank) -> bool
+    {
+        !self.is_joker() && self.rank() == rank
+    }
+
+    pub fn index(&self) -> usize
+    {
+        self.0 as usize
+    }
+
+    pub fn from_index(i: usize) -> Card
+    {
+        if i >= CARD_TYPES { panic!("{} is not a valid card index", i); }
+        Card(i as u8)
+    }
+
+    /// The two characters `TryFrom<&str>` reads back, e.g. `"XD"` or `"JR"`.
+    pub fn code(&self) -> String
+    {
+        match self.0 {
+            52 => "JR".to_string(),
+            53 => "JB".to_string(),
+            _ => format!("{:?}{}", self.rank(), self.suit().code())
         }
     }
 
+    /// The 52 cards of a standard deck, without jokers.
     pub fn full_deck() -> Vec<Card>
     {
-        let suits = Suit::all();
-        let ranks = Rank::all();
-        
-        suits
-        .iter()
-        .flat_map(|s| { ranks.iter().map(move |r| Card::new(*r, *s)) })
-        .collect()
+        (0..52).map(Card::from_index).collect()
+    }
+}
+
+impl TryFrom<u8> for Card {
+    type Error = String;
+
+    fn try_from(index: u8) -> Result<Card, String>
+    {
+        if (index as usize) < CARD_TYPES { Ok(Card(index)) } else { Err(format!("{} is not a valid card index", index)) }
+    }
+}
+
+impl From<Card> for u8 {
+    fn from(card: Card) -> u8 { card.0 }
+}
+
+/// Parses a rank followed by a suit, e.g. `"XD"` or `"a♠"`, or `"JR"` and
+/// `"JB"` for the red and black jokers.
+impl TryFrom<&str> for Card {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Card, String>
+    {
+        let mut chars = s.trim().chars();
+
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('J'), Some('R'), None) | (Some('j'), Some('r'), None) => Ok(Card::joker(Colour::Red)),
+            (Some('J'), Some('B'), None) | (Some('j'), Some('b'), None) => Ok(Card::joker(Colour::Black)),
+            (Some(r), Some(s), None) => Ok(Card::new(Rank::try_from(r)?, Suit::try_from(s)?)),
+            _ => Err(format!("'{}' is not a card", s))
+        }
     }
 }
 
 impl std::fmt::Debug for Card {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("⟨{:?}{:?}⟩", self.rank, self.suit))
+        match self.0 {
+            52 => f.write_str("⟨🃏R⟩"),
+            53 => f.write_str("⟨🃏B⟩"),
+            _ => f.write_fmt(format_args!("⟨{:?}{:?}⟩", self.rank(), self.suit()))
+        }
+    }
+}
+
+/// Most copies of any one card a `CardSet` can hold, i.e. the most decks a
+/// game can be played with.
+pub const MAX_DECKS : usize = 2;
+
+/// A multiset of cards from up to `MAX_DECKS` decks shuffled together.
+/// Copies of the same card are interchangeable, so they are counted rather
+/// than told apart: plane `k` has a bit per `Card::index` held more than
+/// `k` times. With a single deck only the first plane is ever used.
+#[derive(PartialEq, Eq, Clone, Copy, Default)]
+pub struct CardSet([u64; MAX_DECKS]);
+
+impl std::hash::Hash for CardSet {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for plane in &self.0 { state.write_u64(*plane); }
+    }
+}
+
+impl CardSet {
+
+    pub fn new() -> CardSet
+    {
+        CardSet([0; MAX_DECKS])
+    }
+
+    /// One copy of every card.
+    pub fn full() -> CardSet
+    {
+        CardSet::single((1 << 52) - 1)
+    }
+
+    /// Every card from `decks` decks, without jokers.
+    pub fn decks(decks: usize) -> CardSet
+    {
+        if decks > MAX_DECKS { panic!("{} decks is more than {} max", decks, MAX_DECKS); }
+
+        let mut set = CardSet::new();
+        for plane in set.0.iter_mut().take(decks) { *plane = (1 << 52) - 1; }
+        set
+    }
+
+    pub fn suit(suit: Suit) -> CardSet
+    {
+        CardSet::single(0x1fff << (suit.index() * 13))
+    }
+
+    pub fn rank(rank: Rank) -> CardSet
+    {
+        CardSet::single(0x0008_0040_0200_1000 >> (12 - rank.index()))
+    }
+
+    fn single(bits: u64) -> CardSet
+    {
+        let mut set = CardSet::new();
+        set.0[0] = bits;
+        set
+    }
+
+    fn bit(card: &Card) -> u64
+    {
+        1 << card.index()
+    }
+
+    /// Adds a copy of `card`, returning false if the set already holds
+    /// `MAX_DECKS` of them.
+    pub fn insert(&mut self, card: Card) -> bool
+    {
+        let bit = CardSet::bit(&card);
+
+        match self.0.iter_mut().find(|plane| **plane & bit == 0) {
+            Some(plane) => { *plane |= bit; true },
+            None => false
+        }
+    }
+
+    /// Removes one copy of `card`, returning whether there was one.
+    pub fn remove(&mut self, card: &Card) -> bool
+    {
+        let bit = CardSet::bit(card);
+
+        match self.0.iter_mut().rev().find(|plane| **plane & bit != 0) {
+            Some(plane) => { *plane &= !bit; true },
+            None => false
+        }
+    }
+
+    pub fn contains(&self, card: &Card) -> bool
+    {
+        self.0[0] & CardSet::bit(card) != 0
+    }
+
+    /// One copy of each card in the set.
+    pub fn distinct(&self) -> CardSet
+    {
+        CardSet::single(self.0[0])
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.0.iter().map(|plane| plane.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0[0] == 0
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.0 = [0; MAX_DECKS];
+    }
+
+    /// Every card in order, with copies of the same card together.
+    pub fn iter(&self) -> CardSetIter
+    {
+        CardSetIter(*self)
+    }
+}
+
+impl std::ops::BitOr for CardSet {
+    type Output = CardSet;
+    fn bitor(self, other: CardSet) -> CardSet
+    {
+        let mut set = self;
+        for (a, b) in set.0.iter_mut().zip(other.0.iter()) { *a |= b; }
+        set
+    }
+}
+
+impl std::ops::BitAnd for CardSet {
+    type Output = CardSet;
+    fn bitand(self, other: CardSet) -> CardSet
+    {
+        let mut set = self;
+        for (a, b) in set.0.iter_mut().zip(other.0.iter()) { *a &= b; }
+        set
+    }
+}
+
+impl std::ops::Sub for CardSet {
+    type Output = CardSet;
+    /// Removes as many copies of each card as `other` holds.
+    fn sub(self, other: CardSet) -> CardSet
+    {
+        let mut set = CardSet::new();
+
+        for taken in 0..=MAX_DECKS {
+            // The cards `other` holds exactly `taken` copies of.
+            let at_least = if taken == 0 { !0 } else { other.0[taken - 1] };
+            let exactly = at_least & !other.0.get(taken).copied().unwrap_or(0);
+
+            for (k, plane) in set.0.iter_mut().enumerate() {
+                *plane |= self.0.get(k + taken).copied().unwrap_or(0) & exactly;
+            }
+        }
+
+        set
+    }
+}
+
+pub struct CardSetIter(CardSet);
+
+impl Iterator for CardSetIter {
+    type Item = Card;
+
+    fn next(&mut self) -> Option<Card>
+    {
+        if self.0.is_empty() { return None; }
+
+        let card = Card::from_index(self.0.0[0].trailing_zeros() as usize);
+        self.0.remove(&card);
+        Some(card)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        let n = self.0.len();
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for CardSetIter {}
+
+impl IntoIterator for &CardSet {
+    type Item = Card;
+    type IntoIter = CardSetIter;
+
+    fn into_iter(self) -> CardSetIter { self.iter() }
+}
+
+impl std::iter::FromIterator<Card> for CardSet {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> CardSet
+    {
+        let mut set = CardSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Card> for CardSet {
+    fn extend<I: IntoIterator<Item = Card>>(&mut self, iter: I)
+    {
+        for card in iter { self.insert(card); }
+    }
+}
+
+impl std::fmt::Debug for CardSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    #[test]
+    fn ranks_from_numbers_and_letters()
+    {
+        for (i, rank) in Rank::all().iter().enumerate() {
+            assert_eq!(Rank::try_from(i as u8 + 2), Ok(*rank));
+        }
+
+        for bad in [0, 1, 15, 255] {
+            assert!(Rank::try_from(bad).is_err(), "{} is a rank", bad);
+        }
+
+        for (c, rank) in [('a', Rank::Ace), ('K', Rank::King), ('q', Rank::Queen), ('J', Rank::Jack),
+                          ('X', Rank::Ten), ('t', Rank::Ten), ('9', Rank::Nine), ('2', Rank::Two)] {
+            assert_eq!(Rank::try_from(c), Ok(rank));
+        }
+
+        for bad in ['1', '0', 'Z', ' '] {
+            assert!(Rank::try_from(bad).is_err(), "'{}' is a rank", bad);
+        }
+    }
+
+    #[test]
+    fn suits_from_letters_and_symbols()
+    {
+        for suit in Suit::all().iter() {
+            assert_eq!(Suit::try_from(suit.code()), Ok(*suit));
+            assert_eq!(Suit::try_from(suit.code().to_ascii_lowercase()), Ok(*suit));
+            assert_eq!(Suit::try_from(format!("{:?}", suit).chars().next().unwrap()), Ok(*suit));
+        }
+
+        assert!(Suit::try_from('X').is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn every_card_reads_back_its_code()
+    {
+        for i in 0..CARD_TYPES {
+            let c = Card::from_index(i);
+            assert_eq!(Card::try_from(c.code().as_str()), Ok(c));
+            assert_eq!(Card::try_from(u8::from(c)), Ok(c));
+        }
+
+        assert_eq!(card("XD").code(), "XD");
+        assert_eq!(card(" a♠ "), Card::new(Rank::Ace, Suit::Spades));
+        assert_eq!(card("jr"), Card::joker(Colour::Red));
+        assert_eq!(card("JB"), Card::joker(Colour::Black));
+
+        assert!(Card::try_from(CARD_TYPES as u8).is_err());
+        for bad in ["", "A", "ASX", "1S", "AZ", "Jr"] {
+            assert!(Card::try_from(bad).is_err(), "{:?} is a card", bad);
+        }
+    }
+
+    #[test]
+    fn cards_order_by_suit_then_rank_with_jokers_last()
+    {
+        let mut deck : Vec<Card> = ["JB", "2D", "AS", "JR", "KC", "2S", "XH"].iter().map(|c| card(c)).collect();
+        deck.sort();
+
+        let codes : Vec<String> = deck.iter().map(Card::code).collect();
+        assert_eq!(codes, ["2S", "AS", "KC", "XH", "2D", "JR", "JB"]);
+
+        let full = Card::full_deck();
+        assert_eq!(full.len(), 52);
+        assert!(full.windows(2).all(|w| w[0] < w[1]));
+        assert!(full.iter().all(|c| !c.is_joker()));
+    }
+
+    #[test]
+    fn jokers_have_no_rank()
+    {
+        let joker = Card::joker(Colour::Red);
+        assert!(joker.is_joker());
+        assert!(!joker.has_rank(Rank::Jack));
+        assert!(card("JS").has_rank(Rank::Jack));
+        assert!(std::panic::catch_unwind(|| joker.rank()).is_err());
+    }
+
+    fn set(codes: &[&str]) -> CardSet
+    {
+        codes.iter().map(|c| card(c)).collect()
+    }
+
+    #[test]
+    fn sets_insert_remove_and_iterate_in_order()
+    {
+        let mut hand = CardSet::new();
+        assert!(hand.is_empty());
+
+        for code in ["KH", "2S", "JR", "9C"] {
+            assert!(hand.insert(card(code)));
+        }
+
+        assert_eq!(hand.len(), 4);
+        assert!(hand.contains(&card("KH")));
+        assert!(!hand.contains(&card("KD")));
+
+        let codes : Vec<String> = hand.iter().map(|c| c.code()).collect();
+        assert_eq!(codes, ["2S", "9C", "KH", "JR"]);
+        assert_eq!(hand.iter().len(), 4);
+
+        assert!(hand.remove(&card("9C")));
+        assert!(!hand.remove(&card("9C")));
+        assert!(!hand.remove(&card("AS")));
+        assert_eq!(hand, set(&["2S", "KH", "JR"]));
+
+        hand.clear();
+        assert!(hand.is_empty());
+        assert_eq!(hand.iter().next(), None);
+    }
+
+    #[test]
+    fn suits_and_ranks_pick_out_their_cards()
+    {
+        for suit in Suit::all().iter() {
+            let cards = CardSet::suit(*suit);
+            assert_eq!(cards.len(), 13);
+            assert!(cards.iter().all(|c| c.suit() == *suit));
+        }
+
+        for rank in Rank::all().iter() {
+            let cards = CardSet::rank(*rank);
+            assert_eq!(cards.len(), 4);
+            assert!(cards.iter().all(|c| c.rank() == *rank));
+        }
+
+        assert_eq!(CardSet::full().len(), 52);
+        assert!(!CardSet::full().contains(&Card::joker(Colour::Black)));
+    }
+
+    #[test]
+    fn set_operators()
+    {
+        let a = set(&["2S", "3S", "KH"]);
+        let b = set(&["3S", "KH", "AD"]);
+
+        assert_eq!(a | b, set(&["2S", "3S", "KH", "AD"]));
+        assert_eq!(a & b, set(&["3S", "KH"]));
+        assert_eq!(a - b, set(&["2S"]));
+        assert_eq!(b - a, set(&["AD"]));
+        assert_eq!(a - CardSet::new(), a);
+        assert_eq!(a - a, CardSet::new());
+        assert_eq!(CardSet::full() - CardSet::suit(Suit::Spades), CardSet::suit(Suit::Clubs) | CardSet::suit(Suit::Hearts) | CardSet::suit(Suit::Diamonds));
+    }
+
+    #[test]
+    fn copies_of_a_card_stack_in_planes()
+    {
+        let mut hand = set(&["7D", "7D", "2S"]);
+        assert_eq!(hand.len(), 3);
+        assert!(!hand.insert(card("7D")), "held more than {} copies", MAX_DECKS);
+
+        let codes : Vec<String> = hand.iter().map(|c| c.code()).collect();
+        assert_eq!(codes, ["2S", "7D", "7D"]);
+        assert_eq!(hand.distinct(), set(&["2S", "7D"]));
+
+        assert!(hand.remove(&card("7D")));
+        assert!(hand.contains(&card("7D")));
+        assert_eq!(hand, set(&["7D", "2S"]));
+
+        assert!(hand.remove(&card("7D")));
+        assert!(!hand.contains(&card("7D")));
+    }
+
+    #[test]
+    fn set_operators_count_copies()
+    {
+        let two = set(&["7D", "7D", "2S"]);
+        let one = set(&["7D", "AS"]);
+
+        assert_eq!(two - one, set(&["7D", "2S"]));
+        assert_eq!(one - two, set(&["AS"]));
+        assert_eq!(two - two, CardSet::new());
+        assert_eq!(two & one, set(&["7D"]));
+        assert_eq!(one | one, one);
+        assert_eq!((two | one).len(), 4);
+
+        let decks = CardSet::decks(2);
+        assert_eq!(decks.len(), 104);
+        assert_eq!(decks - CardSet::full(), CardSet::full());
+        assert_eq!(decks.distinct(), CardSet::full());
+        assert_eq!(CardSet::decks(1), CardSet::full());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::cards::*;
+use crate::game::Decision;
+use crate::strategy::human::{parse_selection, show_hand, suit_named};
+
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+/// Commands passed to the server as typed.
+const COMMANDS : [&str; 9] = ["NAME", "TABLES", "HOST", "JOIN", "LEAVE", "START", "PLAY", "SUIT", "QUIT"];
+
+const HELP : &str = "Commands: name <name>, tables, host <table> <seats>, join <table> [seat], leave, start, quit.\n\
+                     On your go, pick cards by number or name, or press Enter to pick up; type a suit when asked.";
+
+/// What the client has been told of the hand in play.
+#[derive(Default)]
+struct View
+{
+    players: Vec<String>,
+    hand: Vec<Card>,
+    decision: Option<Decision>
+}
+
+impl View
+{
+    fn name(&self, seat: &str) -> String
+    {
+        seat.parse::<usize>().ok().and_then(|s| self.players.get(s)).cloned().unwrap_or_else(|| seat.to_string())
+    }
+}
+
+fn show_card(code: &str) -> String
+{
+    match Card::try_from(code) {
+        Ok(card) => format!("{:?}", card),
+        Err(_) => code.to_string()
+    }
+}
+
+fn show_cards(codes: &[&str]) -> String
+{
+    let shown : Vec<String> = codes.iter().map(|code| show_card(code)).collect();
+
+    format!("[{}]", shown.join(", "))
+}
+
+fn show_suit(code: &str) -> String
+{
+    match code.chars().next().map(Suit::try_from) {
+        Some(Ok(suit)) => format!("{:?}", suit),
+        _ => code.to_string()
+    }
+}
+
+fn show_state(fields: &[&str]) -> String
+{
+    let field = |key: &str| fields.iter().find_map(|f| f.strip_prefix(key).and_then(|f| f.strip_prefix('='))).unwrap_or("-");
+
+    let mut s = format!("Top card: {}", show_card(field("top")));
+
+    if field("suit") != "-" { s += &format!(", {} nominated", show_suit(field("suit"))); }
+    if field("penalty") != "0" { s += &format!(", {} to pick up", field("penalty")); }
+    if field("skips") != "0" { s += &format!(", {} to miss a go", field("skips")); }
+    if field("reversed") == "true" { s += ", play reversed"; }
+
+    s + &format!(". {} cards left to draw.", field("deck"))
+}
+
+/// A line from the server as a person would want to read it, if at all.
+fn render(line: &str, view: &mut View) -> Option<String>
+{
+    let words : Vec<&str> = line.split_whitespace().collect();
+    let rest = line.split_once(' ').map_or("", |(_, rest)| rest);
+
+    let text = match words[..] {
+        ["WELCOME", name] => format!("Connected as {}. Type 'help' for commands.", name),
+        ["TABLE", name, seated, status] => format!("  {}: {} seated, {}", name, seated, status),
+        ["END"] | ["TURN", _] => return None,
+        ["SEATED", table, seat, who] => format!("{} sits at {} in seat {}.", who, table, seat),
+        ["LEFT", table] => format!("You left {}.", table),
+        ["PLAYERS", ref names @ ..] => {
+            view.players = names.iter().map(|n| n.to_string()).collect();
+            let seats : Vec<String> = names.iter().enumerate().map(|(i, n)| format!("{} {}", i, n)).collect();
+            format!("New hand: {}", seats.join(", "))
+        },
+        ["SEAT", seat] => format!("You are in seat {}.", seat),
+        ["HAND", ref cards @ ..] => {
+            view.hand = cards.iter().filter_map(|c| Card::try_from(*c).ok()).collect();
+            view.decision = None;
+            format!("Your hand: {}", show_hand(&view.hand, &[]))
+        },
+        ["FIRST", card] => format!("The first card is {}.", show_card(card)),
+        ["PLAYED", seat, ref cards @ ..] => format!("{} plays {}.", view.name(seat), show_cards(cards)),
+        ["NOMINATED", seat, suit] => format!("{} nominates {}.", view.name(seat), show_suit(suit)),
+        ["PICKED", seat, n] => format!("{} picks up {}.", view.name(seat), n),
+        ["SKIPPED", seat] => format!("{} misses a go.", view.name(seat)),
+        ["COUNTS", ref counts @ ..] => {
+            let held : Vec<String> = counts.iter().enumerate().map(|(i, n)| format!("{} {}", view.name(&i.to_string()), n)).collect();
+            format!("Cards held: {}", held.join(", "))
+        },
+        ["STATE", ref fields @ ..] => show_state(fields),
+        ["GO", "CHAIN"] => {
+            view.decision = Some(Decision::Chain);
+            "Your go: pick cards by number or name, or press Enter to pick up.".to_string()
+        },
+        ["GO", "SUIT"] => {
+            view.decision = Some(Decision::Suit);
+            "Choose a suit.".to_string()
+        },
+        ["INVALID", ..] => rest.to_string(),
+        ["ERROR", ..] => format!("Error: {}", rest),
+        ["REVEAL", seat, ref cards @ ..] => format!("{} holds {}.", view.name(seat), show_cards(cards)),
+        ["RESULT", winner, ref points @ ..] => {
+            view.decision = None;
+            let scored : Vec<String> = points.iter().enumerate().map(|(i, p)| format!("{} {}", view.name(&i.to_string()), p)).collect();
+
+            match winner {
+                "-" => format!("Nobody can go and the deck is empty. Points: {}", scored.join(", ")),
+                w => format!("{} wins the hand. Points: {}", view.name(w), scored.join(", "))
+            }
+        },
+        _ => line.to_string()
+    };
+
+    Some(text)
+}
+
+/// The protocol line for what was typed, or what to tell the user instead.
+fn translate(input: &str, view: &View) -> Result<String, String>
+{
+    let input = input.trim();
+    let (first, rest) = input.split_once(' ').unwrap_or((input, ""));
+    let command = first.to_uppercase();
+
+    let play = |s: &str| parse_selection(s, &view.hand).map(|chain| {
+        chain.iter().fold("PLAY".to_string(), |line, card| line + " " + &card.code())
+    });
+
+    let suit = |s: &str| suit_named(s).map(|suit| format!("SUIT {}", suit.code())).ok_or(format!("'{}' isn't a suit.", s));
+
+    match (command.as_str(), view.decision) {
+        ("HELP", _) => Err(HELP.to_string()),
+        ("PLAY", _) => play(rest),
+        ("SUIT", _) => suit(rest),
+        (c, _) if COMMANDS.contains(&c) => Ok(format!("{} {}", c, rest).trim_end().to_string()),
+        (_, Some(Decision::Chain)) => play(input),
+        (_, Some(Decision::Suit)) => suit(input),
+        (_, None) if input.is_empty() => Err(String::new()),
+        (_, None) => Err(format!("Can't understand '{}'; type 'help' for commands.", input))
+    }
+}
+
+/// A terminal client for `server::Server`, playing until the server closes
+/// the connection.
+pub fn connect(address: &str, name: Option<&str>) -> Result<(), String>
+{
+    let stream = TcpStream::connect(address).map_err(|e| format!("Could not connect to {}: {}", address, e))?;
+    let reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut writer = stream;
+
+    if let Some(name) = name {
+        writeln!(writer, "NAME {}", name).map_err(|e| e.to_string())?;
+    }
+
+    let view = Arc::new(Mutex::new(View::default()));
+    let typed = view.clone();
+
+    std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+
+        for input in stdin.lock().lines() {
+            let input = match input { Ok(input) => input, Err(_) => break };
+
+            match translate(&input, &typed.lock().unwrap()) {
+                Ok(line) => if writeln!(writer, "{}", line).is_err() { return; },
+                Err(message) => if !message.is_empty() { println!("{}", message) }
+            }
+        }
+
+        let _ = writeln!(writer, "QUIT");
+    });
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+
+        if let Some(text) = render(&line, &mut view.lock().unwrap()) { println!("{}", text); }
+    }
+
+    println!("The server closed the connection.");
+    Ok(())
+}
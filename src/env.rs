@@ -0,0 +1,349 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::neural::{encode, INPUT_SIZE};
+
+/// Observation layout: `neural::encode` of the agent's hand and the chain
+/// built so far, then 1 if a suit is to be nominated, the outstanding skips
+/// / 10, and the other players' hand sizes / 10 in turn order from the
+/// agent, padded with zeros.
+pub const OBSERVATION_SIZE : usize = INPUT_SIZE + 2 + MAX_PLAYERS - 1;
+
+/// Actions below `END_CHAIN` add the card with that `Card::index` to the
+/// chain; `END_CHAIN` plays it, or picks up if it is empty and nothing
+/// goes; the last four
+/// nominate the suit with that `Suit::index`.
+pub const END_CHAIN : usize = CARD_TYPES;
+pub const ACTION_SIZE : usize = END_CHAIN + 1 + 4;
+
+/// Holds the agent's seat; the environment makes its decisions instead.
+struct Agent;
+
+impl Strategy for Agent
+{
+    fn choose_next(&mut self, _: &CardSet, _: &TableState, _: &[Turn]) -> Vec<Card>
+    {
+        unreachable!("The agent's chains come from Env::step")
+    }
+
+    fn choose_suit(&mut self, _: &CardSet, _: &[Turn]) -> Suit
+    {
+        unreachable!("The agent's suits come from Env::step")
+    }
+
+    fn name(&self) -> &str { "Agent" }
+}
+
+/// A game seen from one seat, advanced one agent decision at a time with
+/// the other seats played by their strategies. The chain is built a card
+/// per step so the action space stays fixed.
+pub struct Env
+{
+    pub game: Game,
+    pub seat: usize,
+    chain: Vec<Card>,
+    players: Vec<StrategyType>,
+    rules: Rules
+}
+
+impl Env
+{
+    pub fn new(seat: usize, opponents: &[StrategyType], rules: Rules) -> Result<Env, String>
+    {
+        let count = opponents.len() + 1;
+
+        if !(2..=MAX_PLAYERS).contains(&count) {
+            return Err(format!("{} players is outside 2 to {}", count, MAX_PLAYERS));
+        }
+        if seat >= count { return Err(format!("Seat {} is outside a {} player game", seat, count)); }
+
+        rules.validate()?;
+
+        let mut players = opponents.to_vec();
+        players.insert(seat, StrategyType::new("agent", |_| Box::new(Agent)));
+
+        let game = Game::with_rules(&players, rules.clone());
+
+        let mut env = Env { game, seat, chain: Vec::new(), players, rules };
+        env.begin();
+        Ok(env)
+    }
+
+    /// Deals a new hand and plays up to the agent's first decision.
+    pub fn reset(&mut self)
+    {
+        self.game = Game::with_rules(&self.players, self.rules.clone());
+        self.begin();
+    }
+
+    /// Plays the hand just dealt up to the agent's first decision.
+    fn begin(&mut self)
+    {
+        self.chain.clear();
+        self.advance();
+
+        // Rarely the others finish before the agent has had a go.
+        while self.game.result().is_some() {
+            self.game = Game::with_rules(&self.players, self.rules.clone());
+            self.advance();
+        }
+    }
+
+    fn advance(&mut self)
+    {
+        while self.game.decision().is_some() && self.game.curr_player_id != self.seat {
+            self.game.decide();
+        }
+    }
+
+    /// Applies one action, returning the agent's reward and whether the hand
+    /// finished; a finished hand is replaced with a fresh one. Rewards are
+    /// the hand's points for the agent, negated when lower scores are better.
+    pub fn step(&mut self, action: usize) -> Result<(f32, bool), String>
+    {
+        if !self.allowed(action) { return Err(format!("Action {} is not allowed", action)); }
+
+        match self.game.decision() {
+            Some(Decision::Chain) if action < END_CHAIN => {
+                self.chain.push(Card::from_index(action));
+                return Ok((0.0, false));
+            },
+            Some(Decision::Chain) => self.game.play(std::mem::take(&mut self.chain)),
+            _ => self.game.nominate(Suit::all()[action - END_CHAIN - 1])
+        }
+
+        self.advance();
+
+        match self.game.result() {
+            None => Ok((0.0, false)),
+            Some(result) => {
+                let points = result.points[self.seat] as f32;
+                let reward = if self.rules.scoring.lower_is_better() { -points } else { points };
+
+                self.reset();
+                Ok((reward, true))
+            }
+        }
+    }
+
+    /// Whether `action` is legal now. Ending an empty chain picks up, which
+    /// is only allowed when no card goes.
+    pub fn allowed(&self, action: usize) -> bool
+    {
+        match self.game.decision() {
+            Some(Decision::Chain) if action < END_CHAIN => {
+                let card = Card::from_index(action);
+                let hand = self.game.players[self.seat].hand;
+                let played : CardSet = self.chain.iter().copied().collect();
+
+                let mut chain = self.chain.clone();
+                chain.push(card);
+
+                (hand - played).contains(&card) && is_valid(&self.game.table, &chain)
+            },
+            Some(Decision::Chain) if action == END_CHAIN => {
+                !self.chain.is_empty() || !can_go(&self.game.table, &self.game.players[self.seat].hand)
+            },
+            Some(Decision::Chain) => false,
+            Some(Decision::Suit) => action > END_CHAIN && action < ACTION_SIZE,
+            None => false
+        }
+    }
+
+    pub fn mask(&self, out: &mut [bool])
+    {
+        for (action, allowed) in out.iter_mut().enumerate() { *allowed = self.allowed(action); }
+    }
+
+    pub fn observe(&self, out: &mut [f32])
+    {
+        let hand = &self.game.players[self.seat].hand;
+
+        encode(hand, &self.game.table, &self.chain, &mut out[..INPUT_SIZE]);
+
+        out[INPUT_SIZE] = (self.game.decision() == Some(Decision::Suit)) as usize as f32;
+        out[INPUT_SIZE + 1] = self.game.table.skips as f32 / 10.0;
+
+        let n = self.game.players.len();
+
+        for (i, x) in out[INPUT_SIZE + 2..].iter_mut().enumerate() {
+            *x = if i + 1 < n { self.game.players[(self.seat + i + 1) % n].hand.len() as f32 / 10.0 } else { 0.0 };
+        }
+    }
+}
+
+/// Independent `Env`s stepped together. Observations and masks come as
+/// one flat buffer with a row per env, `OBSERVATION_SIZE` and `ACTION_SIZE`
+/// long.
+pub struct VecEnv
+{
+    pub envs: Vec<Env>
+}
+
+impl VecEnv
+{
+    pub fn new(count: usize, seat: usize, opponents: &[StrategyType], rules: &Rules) -> Result<VecEnv, String>
+    {
+        if count == 0 { return Err("A VecEnv needs at least one game".to_string()); }
+
+        let envs = (0..count)
+                    .map(|_| Env::new(seat, opponents, rules.clone()))
+                    .collect::<Result<Vec<Env>, String>>()?;
+
+        Ok(VecEnv { envs })
+    }
+
+    pub fn reset(&mut self)
+    {
+        for env in &mut self.envs { env.reset(); }
+    }
+
+    /// Steps every env with its action, returning rewards and dones. No env
+    /// is stepped unless every action is allowed.
+    pub fn step(&mut self, actions: &[usize]) -> Result<(Vec<f32>, Vec<bool>), String>
+    {
+        if actions.len() != self.envs.len() {
+            return Err(format!("Expected {} actions, got {}", self.envs.len(), actions.len()));
+        }
+
+        if let Some(i) = (0..actions.len()).find(|i| !self.envs[*i].allowed(actions[*i])) {
+            return Err(format!("Action {} is not allowed in env {}", actions[i], i));
+        }
+
+        let mut rewards = Vec::with_capacity(actions.len());
+        let mut dones = Vec::with_capacity(actions.len());
+
+        for (env, action) in self.envs.iter_mut().zip(actions) {
+            let (reward, done) = env.step(*action)?;
+            rewards.push(reward);
+            dones.push(done);
+        }
+
+        Ok((rewards, dones))
+    }
+
+    pub fn observations(&self) -> Vec<f32>
+    {
+        let mut observations = vec![0.0; self.envs.len() * OBSERVATION_SIZE];
+
+        for (env, row) in self.envs.iter().zip(observations.chunks_mut(OBSERVATION_SIZE)) { env.observe(row); }
+
+        observations
+    }
+
+    pub fn masks(&self) -> Vec<bool>
+    {
+        let mut masks = vec![false; self.envs.len() * ACTION_SIZE];
+
+        for (env, row) in self.envs.iter().zip(masks.chunks_mut(ACTION_SIZE)) { env.mask(row); }
+
+        masks
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strategy::registry::Registry;
+
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    /// An env at seat 0 against one `computer_v1`, with the agent holding
+    /// `hand` and facing `top`.
+    fn rigged(hand: &[&str], top: &str) -> Env
+    {
+        let opponents = [Registry::standard().parse("computer_v1").unwrap()];
+        let mut env = Env::new(0, &opponents, Rules::default()).unwrap();
+
+        // A wild first card would have the agent nominating.
+        while env.game.decision() != Some(Decision::Chain) { env.reset(); }
+
+        env.game.curr_player_id = 0;
+        env.game.players[0].hand = hand.iter().map(|c| card(c)).collect();
+        env.game.table = TableState::new(Arc::new(Rules::default()), card(top));
+        env.game.table.active = false;
+        env
+    }
+
+    fn allowed(env: &Env) -> Vec<usize>
+    {
+        let mut mask = [false; ACTION_SIZE];
+        env.mask(&mut mask);
+        (0..ACTION_SIZE).filter(|a| mask[*a]).collect()
+    }
+
+    #[test]
+    fn only_cards_that_go_can_start_a_chain()
+    {
+        let env = rigged(&["5H", "9S", "3C"], "5S");
+
+        let mut expected = vec![card("9S").index(), card("5H").index()];
+        expected.sort_unstable();
+
+        assert_eq!(allowed(&env), expected);
+        assert!(!env.allowed(END_CHAIN), "nobody picks up while a card goes");
+    }
+
+    #[test]
+    fn picking_up_is_allowed_when_nothing_goes()
+    {
+        let env = rigged(&["4H", "9D"], "5S");
+
+        assert_eq!(allowed(&env), vec![END_CHAIN]);
+    }
+
+    #[test]
+    fn a_chain_can_end_once_started()
+    {
+        let mut env = rigged(&["5H", "6H", "9D"], "5S");
+
+        assert_eq!(env.step(card("5H").index()), Ok((0.0, false)));
+
+        let actions = allowed(&env);
+        assert!(actions.contains(&card("6H").index()));
+        assert!(actions.contains(&END_CHAIN));
+        assert!(!actions.contains(&card("5H").index()), "a card can't be added twice");
+        assert!(!actions.contains(&card("9D").index()));
+    }
+
+    #[test]
+    fn only_suits_can_be_nominated()
+    {
+        let mut env = rigged(&["AS", "9D"], "5S");
+
+        env.step(card("AS").index()).unwrap();
+        env.step(END_CHAIN).unwrap();
+
+        assert_eq!(env.game.decision(), Some(Decision::Suit));
+        assert_eq!(allowed(&env), (END_CHAIN + 1..ACTION_SIZE).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn vec_env_rows_are_flat()
+    {
+        let opponents = [Registry::standard().parse("computer_v1").unwrap()];
+        let mut envs = VecEnv::new(3, 1, &opponents, &Rules::default()).unwrap();
+
+        assert_eq!(envs.observations().len(), 3 * OBSERVATION_SIZE);
+        assert_eq!(envs.masks().len(), 3 * ACTION_SIZE);
+
+        let masks = envs.masks();
+        let actions : Vec<usize> = masks.chunks(ACTION_SIZE).map(|row| row.iter().position(|a| *a).unwrap()).collect();
+        assert!(envs.step(&actions).is_ok());
+
+        let mut bad = actions.clone();
+        bad[2] = ACTION_SIZE;
+        assert!(envs.step(&bad).is_err());
+        assert!(envs.step(&actions[..2]).is_err());
+
+        assert!(VecEnv::new(0, 0, &opponents, &Rules::default()).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::rules::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::heuristic::*;
+
+#[derive(Clone)]
+pub struct EvolutionConfig
+{
+    pub start: HeuristicParams,
+    pub population: usize,
+    pub survivors: usize,
+    pub generations: usize,
+    pub games_per_evaluation: usize,
+    pub mutation_rate: f64,
+    pub mutation_scale: f32,
+    pub opponents: Vec<StrategyType>,
+    pub rules: Rules,
+    /// Seeds the mutations and the hands, so a seed evolves the same weights.
+    pub seed: u64
+}
+
+impl Default for EvolutionConfig
+{
+    fn default() -> EvolutionConfig
+    {
+        EvolutionConfig
+        {
+            start: HeuristicParams::default(),
+            population: 16,
+            survivors: 4,
+            generations: 20,
+            games_per_evaluation: 500,
+            mutation_rate: 0.3,
+            mutation_scale: 0.25,
+            opponents: ["computer_v1", "computer_v2", "computer_v1"]
+                .iter()
+                .map(|s| StrategyType::parse(s).unwrap())
+                .collect(),
+            rules: Rules::default(),
+            seed: 0
+        }
+    }
+}
+
+/// Average score of a heuristic seated against the configured opponents,
+/// rotating its seat so it doesn't always go first. Heuristics evaluated
+/// with the same seed get the same deals.
+pub fn evaluate(params: HeuristicParams, config: &EvolutionConfig, seed: u64) -> f64
+{
+    let player = HeuristicStrategy::strategy_type("heuristic", params);
+    let points = rotating_points(&player, &config.opponents, &config.rules, config.games_per_evaluation, seed);
+
+    points.iter().sum::<i32>() as f64 / points.len() as f64
+}
+
+fn mutate<R: Rng>(params: &mut HeuristicParams, config: &EvolutionConfig, rng: &mut R)
+{
+    let mut perturb = |x: &mut f32, scale: f32| {
+        if rng.gen_bool(config.mutation_rate) {
+            *x += rng.gen_range(-scale..scale);
+            *x = x.max(0.0);
+        }
+    };
+
+    let pick_up_scale = config.mutation_scale * HeuristicParams::default().pick_up_weighting;
+
+    perturb(&mut params.pick_up_weighting, pick_up_scale);
+    perturb(&mut params.defensive_retention, config.mutation_scale * 20.0);
+
+    for w in params.dump_priority.iter_mut() {
+        perturb(w, config.mutation_scale);
+    }
+}
+
+fn crossover<R: Rng>(a: &HeuristicParams, b: &HeuristicParams, rng: &mut R) -> HeuristicParams
+{
+    let mut child = *a;
+
+    if rng.gen_bool(0.5) { child.pick_up_weighting = b.pick_up_weighting; }
+    if rng.gen_bool(0.5) { child.defensive_retention = b.defensive_retention; }
+
+    for (i, w) in child.dump_priority.iter_mut().enumerate() {
+        if rng.gen_bool(0.5) { *w = b.dump_priority[i]; }
+    }
+
+    child
+}
+
+/// Evolves heuristic weights starting from `config.start`, keeping the best
+/// `survivors` of each generation and breeding the rest from them. After
+/// each generation `progress` is given its number and its best and median
+/// average scores. Each generation is scored on its own deals, the same for
+/// every heuristic in it.
+pub fn evolve<F>(config: &EvolutionConfig, mut progress: F) -> Result<(HeuristicParams, f64), String>
+where
+    F : FnMut(usize, f64, f64)
+{
+    if config.survivors == 0 || config.survivors > config.population {
+        return Err(format!("Survivors must be between one and the population size, not {} of {}", config.survivors, config.population));
+    }
+
+    if config.games_per_evaluation == 0 {
+        return Err("Each heuristic must be evaluated over at least one game".to_string());
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let mut population : Vec<HeuristicParams> = (0..config.population).map(|i| {
+        let mut params = config.start;
+        if i > 0 { mutate(&mut params, config, &mut rng); }
+        params
+    }).collect();
+
+    let lower_is_better = config.rules.scoring.lower_is_better();
+    let better = |a: f64, b: f64| if lower_is_better { a < b } else { a > b };
+
+    let mut best = (population[0], if lower_is_better { f64::INFINITY } else { f64::NEG_INFINITY });
+
+    for generation in 0..config.generations {
+
+        let seed = rng.gen();
+
+        let mut ranked : Vec<(HeuristicParams, f64)> = population
+                .iter()
+                .map(|p| (*p, evaluate(*p, config, seed)))
+                .collect();
+
+        ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        if !lower_is_better { ranked.reverse(); }
+
+        if better(ranked[0].1, best.1) { best = ranked[0]; }
+
+        progress(generation, ranked[0].1, ranked[ranked.len() / 2].1);
+
+        let survivors : Vec<HeuristicParams> = ranked
+                .iter()
+                .take(config.survivors)
+                .map(|(p, _)| *p)
+                .collect();
+
+        population = survivors.clone();
+
+        while population.len() < config.population {
+            let a = survivors.choose(&mut rng).unwrap();
+            let b = survivors.choose(&mut rng).unwrap();
+            let mut child = crossover(a, b, &mut rng);
+            mutate(&mut child, config, &mut rng);
+            population.push(child);
+        }
+    }
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn small() -> EvolutionConfig
+    {
+        EvolutionConfig {
+            population: 4,
+            survivors: 2,
+            generations: 2,
+            games_per_evaluation: 20,
+            opponents: vec![StrategyType::parse("computer_v1").unwrap()],
+            seed: 7,
+            ..EvolutionConfig::default()
+        }
+    }
+
+    #[test]
+    fn a_seed_evolves_the_same_weights()
+    {
+        let config = small();
+        let mut generations = Vec::new();
+
+        let first = evolve(&config, |generation, best, median| generations.push((generation, best, median))).unwrap();
+        let second = evolve(&config, |_, _, _| ()).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(generations.iter().map(|g| g.0).collect::<Vec<usize>>(), [0, 1]);
+        assert!(generations.iter().all(|(_, best, median)| best <= median), "lower scores are better");
+        assert_eq!(first.1, generations.iter().map(|g| g.1).fold(f64::INFINITY, f64::min));
+    }
+
+    #[test]
+    fn heuristics_evaluated_with_a_seed_get_the_same_deals()
+    {
+        let config = small();
+        let params = HeuristicParams::default();
+
+        assert_eq!(evaluate(params, &config, 3), evaluate(params, &config, 3));
+    }
+
+    #[test]
+    fn bad_configs_are_errors()
+    {
+        for (population, survivors) in [(4, 0), (4, 5), (0, 0)] {
+            let config = EvolutionConfig { population, survivors, ..small() };
+            assert!(evolve(&config, |_, _, _| ()).is_err(), "{} of {} survived", survivors, population);
+        }
+
+        let config = EvolutionConfig { games_per_evaluation: 0, ..small() };
+        assert!(evolve(&config, |_, _, _| ()).is_err());
+    }
+
+    #[test]
+    fn children_take_each_weight_from_a_parent_and_mutate_from_there()
+    {
+        let mut rng = StdRng::seed_from_u64(0);
+        let a = HeuristicParams::default();
+        let b = HeuristicParams { pick_up_weighting: 0.0, dump_priority: [2.0; 13], defensive_retention: 5.0 };
+
+        for _ in 0..20 {
+            let child = crossover(&a, &b, &mut rng);
+            assert!(child.pick_up_weighting == a.pick_up_weighting || child.pick_up_weighting == b.pick_up_weighting);
+            assert!(child.defensive_retention == a.defensive_retention || child.defensive_retention == b.defensive_retention);
+            assert!(child.dump_priority.iter().all(|w| *w == 1.0 || *w == 2.0));
+
+            let mut mutated = child;
+            mutate(&mut mutated, &EvolutionConfig { mutation_rate: 1.0, ..small() }, &mut rng);
+            assert_ne!(mutated, child);
+            assert!(mutated.pick_up_weighting >= 0.0 && mutated.defensive_retention >= 0.0);
+            assert!(mutated.dump_priority.iter().all(|w| *w >= 0.0));
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::rules::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::cfr::*;
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct ResponseConfig
+{
+    pub training_games: usize,
+    pub evaluation_games: usize,
+    pub exploration: f64,
+    /// Seeds the exploration and the hands; the reference and the best
+    /// response are evaluated on the same deals.
+    pub seed: u64
+}
+
+impl Default for ResponseConfig
+{
+    fn default() -> ResponseConfig
+    {
+        ResponseConfig
+        {
+            training_games: 20_000,
+            evaluation_games: 10_000,
+            exploration: 0.2,
+            seed: 0
+        }
+    }
+}
+
+/// A seat's average points per hand and the standard error of that average.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate
+{
+    pub mean: f64,
+    pub std_error: f64
+}
+
+impl Estimate
+{
+    fn from_points(points: &[i32]) -> Estimate
+    {
+        let n = points.len().max(1) as f64;
+        let mean = points.iter().map(|p| *p as f64).sum::<f64>() / n;
+        let variance = points.iter().map(|p| (*p as f64 - mean).powi(2)).sum::<f64>() / n;
+
+        Estimate { mean, std_error: (variance / n).sqrt() }
+    }
+}
+
+/// How a strategy and a best response to the same opponents compare.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exploitation
+{
+    pub reference: Estimate,
+    pub response: Estimate,
+    /// Points a hand the best response does better by, in whichever
+    /// direction the scoring counts as better.
+    pub gain: f64
+}
+
+/// Learns the average points each `Move` leads to in each `InfoSet`.
+#[derive(Default)]
+struct Responder
+{
+    values: HashMap<InfoSet, [(f64, usize); MOVES]>
+}
+
+impl Responder
+{
+    fn choose<R: Rng>(&self, key: &InfoSet, legal: &[bool; MOVES], sign: f64, exploration: f64, rng: &mut R) -> usize
+    {
+        let options : Vec<usize> = (0..MOVES).filter(|m| legal[*m]).collect();
+
+        if rng.gen_bool(exploration) { return options[rng.gen_range(0..options.len())]; }
+
+        let value = |m: usize| match self.values.get(key) {
+            Some(v) if v[m].1 > 0 => sign * v[m].0 / v[m].1 as f64,
+            _ => f64::NEG_INFINITY
+        };
+
+        // Untried moves fall back on the greedy chain where there is one.
+        let fallback = if legal[Move::Greedy.index()] { Move::Greedy.index() } else { options[0] };
+
+        options
+            .into_iter()
+            .filter(|m| value(*m) > f64::NEG_INFINITY)
+            .max_by(|a, b| value(*a).partial_cmp(&value(*b)).unwrap())
+            .unwrap_or(fallback)
+    }
+
+    fn learn(&mut self, visited: &[(InfoSet, usize)], points: i32)
+    {
+        for (key, m) in visited {
+            let value = &mut self.values.entry(*key).or_insert([(0.0, 0); MOVES])[*m];
+            value.0 += points as f64;
+            value.1 += 1;
+        }
+    }
+}
+
+/// Approximates a best response to fixed `opponents` and returns its average
+/// points against them.
+///
+/// The responder plays abstract `Move`s, learning each one's average points
+/// in each CFR `InfoSet` over the training hands while exploring at random,
+/// then plays greedily on what it learnt for the evaluation hands. Confined
+/// to the abstraction, it can only find part of what a true best response
+/// would.
+pub fn best_response(opponents: &[StrategyType], rules: &Rules, config: &ResponseConfig) -> Estimate
+{
+    let mut responder = Responder::default();
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let sign = if rules.scoring.lower_is_better() { -1.0 } else { 1.0 };
+
+    let mut points = Vec::with_capacity(config.evaluation_games);
+
+    for g in 0..config.training_games + config.evaluation_games {
+
+        let training = g < config.training_games;
+        let exploration = if training { config.exploration } else { 0.0 };
+
+        // The evaluation hands are dealt as `rotating_points` deals them.
+        let (seat, seed) = match g.checked_sub(config.training_games) {
+            Some(i) => (i % (opponents.len() + 1), config.seed.wrapping_add(i as u64)),
+            None => (g % (opponents.len() + 1), rng.gen())
+        };
+
+        let mut game = Game::with_seed(&seated(&suit_nominator(), opponents, seat), rules.clone(), seed);
+        let mut visited = Vec::new();
+
+        while let Some(decision) = game.decision() {
+
+            if decision == Decision::Suit || game.curr_player_id != seat { game.decide(); continue; }
+
+            let hand = game.players[seat].hand;
+            let mut chains = move_chains(&game.table, &hand);
+            let legal = legal_moves(&chains);
+
+            let others = game.players.iter().enumerate().filter(|(p, _)| *p != seat).map(|(_, p)| p.hand.len());
+            let key = InfoSet::new(&game.table, &hand, others.min().unwrap());
+
+            let m = responder.choose(&key, &legal, sign, exploration, &mut rng);
+
+            visited.push((key, m));
+            game.play(chains[m].take().unwrap());
+        }
+
+        let result = game.result().unwrap().points[seat];
+
+        if training { responder.learn(&visited, result); } else { points.push(result); }
+    }
+
+    Estimate::from_points(&points)
+}
+
+/// How much a best response to `opponents` gains over `reference` playing
+/// the same seats and deals; seating a strategy against copies of itself
+/// measures how exploitable it is.
+pub fn exploit(reference: &StrategyType, opponents: &[StrategyType], rules: &Rules, config: &ResponseConfig) -> Exploitation
+{
+    let reference = Estimate::from_points(&rotating_points(reference, opponents, rules, config.evaluation_games, config.seed));
+    let response = best_response(opponents, rules, config);
+
+    let gain = if rules.scoring.lower_is_better() { reference.mean - response.mean } else { response.mean - reference.mean };
+
+    Exploitation { reference, response, gain }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::cards::*;
+
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    fn quick() -> ResponseConfig
+    {
+        ResponseConfig { training_games: 200, evaluation_games: 100, exploration: 0.2, seed: 5 }
+    }
+
+    fn key() -> InfoSet
+    {
+        let table = TableState::new(Arc::new(Rules::default()), Card::try_from("5S").unwrap());
+        InfoSet::new(&table, &CardSet::new(), 1)
+    }
+
+    #[test]
+    fn estimates_give_the_mean_and_its_standard_error()
+    {
+        let estimate = Estimate::from_points(&[1, 3, 1, 3]);
+
+        assert_eq!(estimate.mean, 2.0);
+        assert_eq!(estimate.std_error, 0.5);
+        assert_eq!(Estimate::from_points(&[]).mean, 0.0);
+    }
+
+    #[test]
+    fn the_responder_plays_the_best_learnt_move()
+    {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut responder = Responder::default();
+        let mut legal = [false; MOVES];
+        legal[Move::PickUp.index()] = true;
+        legal[Move::Single.index()] = true;
+
+        assert_eq!(responder.choose(&key(), &legal, 1.0, 0.0, &mut rng), Move::PickUp.index(), "untried moves go to the first legal");
+
+        legal[Move::Greedy.index()] = true;
+        assert_eq!(responder.choose(&key(), &legal, 1.0, 0.0, &mut rng), Move::Greedy.index(), "untried moves go to the greedy chain");
+
+        responder.learn(&[(key(), Move::Single.index()), (key(), Move::PickUp.index())], 10);
+        responder.learn(&[(key(), Move::Single.index())], 30);
+
+        assert_eq!(responder.choose(&key(), &legal, 1.0, 0.0, &mut rng), Move::Single.index());
+        assert_eq!(responder.choose(&key(), &legal, -1.0, 0.0, &mut rng), Move::PickUp.index());
+
+        for _ in 0..50 {
+            assert!(legal[responder.choose(&key(), &legal, 1.0, 1.0, &mut rng)]);
+        }
+    }
+
+    #[test]
+    fn a_seed_gives_the_same_exploitation()
+    {
+        let bot = computer_v1::ComputerStrategyV1::strategy_type("computer_v1");
+        let opponents = [StrategyType::parse("random").unwrap()];
+
+        let first = exploit(&bot, &opponents, &Rules::default(), &quick());
+
+        assert_eq!(first, exploit(&bot, &opponents, &Rules::default(), &quick()));
+        assert_eq!(first.gain, first.reference.mean - first.response.mean, "lower scores are better");
+        assert_ne!(first, exploit(&bot, &opponents, &Rules::default(), &ResponseConfig { seed: 6, ..quick() }));
+    }
+
+    #[test]
+    fn the_reference_is_dealt_what_the_response_is()
+    {
+        let bot = computer_v1::ComputerStrategyV1::strategy_type("computer_v1");
+        let opponents = [bot.clone()];
+        let config = ResponseConfig { training_games: 0, ..quick() };
+
+        // Untrained, the responder always plays the greedy chain as
+        // `computer_v1` does, so on the same deals it scores the same.
+        let result = exploit(&bot, &opponents, &Rules::default(), &config);
+
+        assert_eq!(result.reference, result.response);
+        assert_eq!(result.gain, 0.0);
+    }
+}
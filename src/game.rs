@@ -1,13 +1,15 @@
-use rand::thread_rng;
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 
 use crate::player::*;
 use crate::cards::*;
 use crate::strategy::*;
-use crate::blackjack::*;
+use crate::rules::*;
 
 use std::fmt::Debug;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Action
@@ -26,6 +28,24 @@ pub struct Turn
     pub action: Action
 }
 
+/// How a hand ended: who went out (nobody if no one could go), the cards
+/// each player was left holding and the points each scored, by seat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandResult
+{
+    pub winner: Option<usize>,
+    pub remaining: Vec<CardSet>,
+    pub points: Vec<i32>
+}
+
+/// What a game is waiting on the current player for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision
+{
+    Chain,
+    Suit
+}
+
 #[derive(Debug)]
 pub struct Game
 {
@@ -33,32 +53,81 @@ pub struct Game
     pub deck: Vec<Card>,
     pub discard_pile: Vec<Card>,
     pub log: Vec<Turn>,
-    pub curr_player_id: usize
+    pub table: TableState,
+    pub curr_player_id: usize,
+    pub decks: usize,
+    idle_turns: usize,
+    finished_on_penalty: bool,
+    awaiting_suit: bool,
+    result: Option<HandResult>,
+    rng: StdRng
 }
 
 impl Game
 {
     pub fn new(player_types: &[StrategyType]) -> Game
     {
-        if player_types.len() < 2 { panic!("Two players min") };
-        if player_types.len() > 6 { panic!("Six players max") };
+        Game::with_rules(player_types, Rules::default())
+    }
+
+    pub fn with_rules(player_types: &[StrategyType], rules: Rules) -> Game
+    {
+        Game::with_strategies(player_types
+                                .iter()
+                                .map(|pt| make_strategy(pt, player_types))
+                                .collect(),
+                              rules)
+    }
 
-        let mut players = Vec::<Player>::new();
-        
-        for (i, pt) in player_types.iter().enumerate() {
+    /// As `with_rules`, with the shuffles and any strategy that plays at
+    /// random seeded from `seed`, so the same seed plays the same hand.
+    pub fn with_seed(player_types: &[StrategyType], rules: Rules, seed: u64) -> Game
+    {
+        let mut game = Game::build(player_types
+                                    .iter()
+                                    .map(|pt| make_strategy(pt, player_types))
+                                    .collect(),
+                                   rules,
+                                   StdRng::seed_from_u64(seed));
 
-            let strategy = make_strategy(pt, player_types);
+        for player in &mut game.players { player.strategy.seed(game.rng.gen()); }
 
+        game
+    }
+
+    pub fn with_strategies(strategies: Vec<Box<dyn Strategy + Send>>, rules: Rules) -> Game
+    {
+        Game::build(strategies, rules, StdRng::from_rng(thread_rng()).unwrap())
+    }
+
+    fn build(strategies: Vec<Box<dyn Strategy + Send>>, rules: Rules, rng: StdRng) -> Game
+    {
+        if strategies.len() < 2 { panic!("Two players min") };
+        if strategies.len() > MAX_PLAYERS { panic!("{} players max", MAX_PLAYERS) };
+        if let Err(e) = rules.validate() { panic!("{}", e) };
+
+        let decks = decks_for(strategies.len());
+
+        let mut players = Vec::<Player>::new();
+        
+        for (i, strategy) in strategies.into_iter().enumerate() {
             players.push(Player::new(
                 format!("Player {} ({})", i, strategy.name()), strategy));
         }
 
         let mut game = Game {
-            players: players,
+            players,
             deck: Vec::<Card>::new(),
             discard_pile: Vec::<Card>::new(),
             curr_player_id: 0,
-            log: Vec::<Turn>::new()
+            log: Vec::<Turn>::new(),
+            table: TableState::new(Arc::new(rules), Card::from_index(0)),
+            decks,
+            idle_turns: 0,
+            finished_on_penalty: false,
+            awaiting_suit: false,
+            result: None,
+            rng
         };
 
         game.populate_deck();
@@ -67,10 +136,18 @@ impl Game
         let first = game.draw();
         
         game.discard_pile.push(first);
-        game.log.push(Turn { player: None, action: Action::First(first) });
+        game.record(Turn { player: None, action: Action::First(first) });
+        game.awaiting_suit = game.table.rules.effect(first) == Some(Effect::Wild);
         game
     }
 
+    /// Appends a turn to the log, keeping the table state in step with it.
+    pub fn record(&mut self, turn: Turn)
+    {
+        self.table.apply(&turn);
+        self.log.push(turn);
+    }
+
     fn deal(&mut self)
     {
         for _ in 0..STARTING_CARD_COUNT {
@@ -83,166 +160,229 @@ impl Game
 
     pub fn populate_deck(&mut self)
     {
-        let mut deck : Vec<Card> = Card::full_deck();
-        deck.shuffle(&mut thread_rng());
+        let mut deck : Vec<Card> = self.table.rules.deck(self.decks).iter().collect();
+        deck.shuffle(&mut self.rng);
         self.deck = deck;
     }
 
+    /// Draws a card, shuffling all but the top of the discard pile back in
+    /// once the deck runs out. Callers check `cards_available` first.
     pub fn draw(&mut self) -> Card
     {
         match self.deck.pop() {
-            Some(c) => return c,
+            Some(c) => c,
             None => {
+                let top = self.discard_pile.pop();
                 self.deck.append(&mut self.discard_pile);
-                self.deck.shuffle(&mut thread_rng());
+                self.deck.shuffle(&mut self.rng);
+                self.discard_pile.extend(top);
                 self.deck.pop().unwrap()
             }
         }
     }
 
-    fn player_should_skip(&self) -> bool
+    /// Cards that can still be drawn: the deck plus everything but the top
+    /// of the discard pile.
+    pub fn cards_available(&self) -> usize
     {
-        match &self.log.last().unwrap().action {
-            Action::Played(c) => match c.last().unwrap() {
-                Card { rank: Rank::Val(8), suit: _ } => true,
-                _ => false
+        self.deck.len() + self.discard_pile.len().saturating_sub(1)
+    }
+
+    fn pick_up(&mut self, n: usize) -> usize
+    {
+        let n = n.min(self.cards_available());
+
+        for _ in 0..n {
+            let next = self.draw();
+            self.players[self.curr_player_id].hand.insert(next);
+        }
+
+        self.record(Turn {
+            player: Some(self.curr_player_id),
+            action: Action::PickedUp(n)
+        });
+
+        n
+    }
+
+    /// What the current player has to decide next, or `None` once the hand
+    /// is over.
+    pub fn decision(&self) -> Option<Decision>
+    {
+        match (&self.result, self.awaiting_suit) {
+            (Some(_), _) => None,
+            (None, true) => Some(Decision::Suit),
+            (None, false) => Some(Decision::Chain)
+        }
+    }
+
+    /// The outcome, once the hand is over.
+    pub fn result(&self) -> Option<&HandResult>
+    {
+        self.result.as_ref()
+    }
+
+    /// Lets the current player's strategy make the pending decision.
+    pub fn decide(&mut self)
+    {
+        match self.decision() {
+            Some(Decision::Chain) => {
+                let chain = self.players[self.curr_player_id].choose_next(&self.table, &self.log);
+                self.play(chain);
             },
-            Action::First(Card { rank: Rank::Val(8), suit: _ }) => true,
-            _ => false
+            Some(Decision::Suit) => {
+                let suit = self.players[self.curr_player_id].choose_suit(&self.log);
+                self.nominate(suit);
+            },
+            None => panic!("The hand is over")
         }
     }
 
-    pub fn run(&mut self) -> Vec<i32>
+    /// Plays a chain for the current player, or picks up if it is empty.
+    pub fn play(&mut self, chain: Vec<Card>)
     {
-        if crate::PRINT { println!("First card is {:?}", self.discard_pile.last().unwrap()); }
+        if self.decision() != Some(Decision::Chain) { panic!("Not waiting for a chain"); }
+
+        if chain.is_empty() {
+            self.pass();
+        } else {
 
-        if self.discard_pile.last().unwrap().rank == Rank::Ace {
-            let suit = self.players[self.curr_player_id].choose_suit(&self.log);
+            if !is_valid(&self.table, &chain) { 
+                panic!("{} tried to play an invalid strategy!", self.players[self.curr_player_id].name);
+            }
+                    
+            self.discard_pile.extend(&chain);
             
-            self.log.push(Turn { 
+            self.record(Turn { 
                 player: Some(self.curr_player_id), 
-                action: Action::Nominated(suit) 
+                action: Action::Played(chain.clone()) 
             });
 
-            if crate::PRINT { println!("{} nominates: {:?}", self.players[self.curr_player_id].name, suit); }
+            if crate::PRINT { println!("{} plays: {:?}", self.players[self.curr_player_id].name, &chain); }
+
+            for c in &chain { self.players[self.curr_player_id].hand.remove(c); }
+
+            self.idle_turns = 0;
+
+            let going_out = self.players[self.curr_player_id].hand.is_empty();
+
+            self.finished_on_penalty = going_out && self.table.rules.penalty_value(*chain.last().unwrap()) > 0;
+
+            match self.table.rules.effect(*chain.last().unwrap()) {
+                Some(Effect::Wild) => {
+                    self.awaiting_suit = true;
+                    return;
+                },
+                Some(Effect::Cover) => {
+                    
+                    self.pick_up(1);
+
+                    if crate::PRINT { println!("{} finished with {:?} so picks up.", 
+                        self.players[self.curr_player_id].name, chain.last().unwrap()); }
+                },
+                _ => ()
+            }
         }
 
-        loop {
-            
-            let chain = self.players[self.curr_player_id].choose_next(&self.log);
+        self.end_turn();
+    }
 
-            if chain.len() == 0 {
-                
-                let raw_penalty = outstanding_penalty(&self.log);
+    /// Nominates a suit for the current player after a wild card.
+    pub fn nominate(&mut self, suit: Suit)
+    {
+        if self.decision() != Some(Decision::Suit) { panic!("Not waiting for a suit"); }
 
-                let penalty = raw_penalty
-                                .min(self.deck.len() + self.discard_pile.len());
+        // A wild first card has the first player nominate before anyone
+        // has played, and then take their turn as usual.
+        let opening = matches!(self.log.last().map(|t| &t.action), Some(Action::First(_)));
 
-                if penalty > 0 {
+        self.record(Turn { 
+            player: Some(self.curr_player_id),
+            action: Action::Nominated(suit) 
+        });
 
-                    for _ in 0..penalty {
-                        let next = self.draw();
-                        self.players[self.curr_player_id].hand.insert(next);
-                    }
+        if crate::PRINT { println!("{} nominates: {:?}", self.players[self.curr_player_id].name, suit); }
 
-                    self.log.push(Turn {
-                        player: Some(self.curr_player_id),
-                        action: Action::PickedUp(penalty)
-                    });
+        self.awaiting_suit = false;
 
-                    if crate::PRINT { 
-                        
-                        println!("{} picks up {}.", self.players[self.curr_player_id].name, penalty); 
-                    
-                        if penalty < raw_penalty {
-                            println!("{} picks up {} (deck constrained).", self.players[self.curr_player_id].name, penalty);
-                        } else  {
-                            println!("{} picks up {}.", self.players[self.curr_player_id].name, penalty);
-                        }
-                    }
-
-                } else if self.player_should_skip() {
-                    
-                    self.log.push(Turn {
-                        player: Some(self.curr_player_id),
-                        action: Action::Skipped
-                    });
+        if !opening { self.end_turn(); }
+    }
 
-                    if crate::PRINT { println!("{} misses a go.", self.players[self.curr_player_id].name); }
+    fn pass(&mut self)
+    {
+        let raw_penalty = self.table.penalty;
 
-                } else {
-                    
-                    let pick_up = self.draw();
-                    
-                    self.players[self.curr_player_id].hand.insert(pick_up);
-                    
-                    self.log.push(Turn {
-                        player: Some(self.curr_player_id),
-                        action: Action::PickedUp(1)
-                    });
+        let penalty = raw_penalty.min(self.cards_available());
 
-                    if crate::PRINT { println!("{} can't go; picks up {}.", self.players[self.curr_player_id].name, 1); }
-                }
+        if penalty > 0 {
 
-            } else {
+            self.pick_up(penalty);
 
-                if !is_valid(&self.log, &chain) { 
-                    panic!("{} tried to play an invalid strategy!", self.players[self.curr_player_id].name);
-                }
-                else {
-                    
-                    self.discard_pile.extend(&chain);
-                    
-                    self.log.push(Turn { 
-                        player: Some(self.curr_player_id), 
-                        action: Action::Played(chain.clone()) 
-                    });
-
-                    if crate::PRINT { println!("{} plays: {:?}", self.players[self.curr_player_id].name, &chain); }
-
-                    match chain.last().unwrap().rank {
-                        Rank::Ace => {
-                            let suit = self.players[self.curr_player_id].choose_suit(&self.log);
-                        
-                            self.log.push(Turn { 
-                                player: Some(self.curr_player_id),
-                                action: Action::Nominated(suit) 
-                            });
-    
-                            if crate::PRINT { println!("{} nominates: {:?}", self.players[self.curr_player_id].name, suit); }
-                        },
-                        Rank::King => {
-                            
-                            let pick_up = self.draw();
-                            self.players[self.curr_player_id].hand.insert(pick_up);
-                            
-                            self.log.push(Turn {
-                                player: Some(self.curr_player_id),
-                                action: Action::PickedUp(1)
-                            });
-
-                            if crate::PRINT { println!("{} finished with {:?} so picks up.", 
-                                self.players[self.curr_player_id].name, chain.last().unwrap()); }
-                        },
-                        _ => ()
-                    }
-                    
-                    for c in &chain { self.players[self.curr_player_id].hand.remove(&c); }
+            if crate::PRINT { 
+                
+                if penalty < raw_penalty {
+                    println!("{} picks up {} (deck constrained).", self.players[self.curr_player_id].name, penalty);
+                } else  {
+                    println!("{} picks up {}.", self.players[self.curr_player_id].name, penalty);
                 }
             }
 
+        } else if self.table.skips > 0 {
+            
+            self.record(Turn {
+                player: Some(self.curr_player_id),
+                action: Action::Skipped
+            });
+
+            if crate::PRINT { println!("{} misses a go.", self.players[self.curr_player_id].name); }
+
+        } else {
+            
+            let picked_up = self.pick_up(1);
+
+            if crate::PRINT { println!("{} can't go; picks up {}.", self.players[self.curr_player_id].name, picked_up); }
+
+            if picked_up == 0 {
+                self.idle_turns += 1;
+            }
+        }
+    }
+
+    fn end_turn(&mut self)
+    {
+        loop {
             if self.players[self.curr_player_id].hand.is_empty() {
                 if crate::PRINT { println!("{} wins!", self.players[self.curr_player_id].name); }
-                break;
+                self.finish(Some(self.curr_player_id));
+                return;
+            } else if self.idle_turns == self.players.len() {
+                if crate::PRINT { println!("Nobody can go and the deck is empty."); }
+                self.finish(None);
+                return;
+            }
+
+            let n = self.players.len();
+            let step = if self.table.reversed { n - 1 } else { 1 };
+            self.curr_player_id = (self.curr_player_id + step) % n;
+
+            // Once one player has missed a go, anyone else skipped by the
+            // same chain has no chance to answer it.
+            if self.table.skips > 0 && !self.table.active {
+                self.pass();
             } else {
-                self.curr_player_id += 1;
-                if self.curr_player_id == self.players.len() { self.curr_player_id = 0; }
+                return;
             }
         }
+    }
 
-        for p in self.players.iter_mut()
+    fn finish(&mut self, winner: Option<usize>)
+    {
+        let remaining : Vec<CardSet> = self.players.iter().map(|p| p.hand).collect();
+        let points = self.table.rules.score_hand(&remaining, winner, self.finished_on_penalty);
+
+        for (p, points) in self.players.iter_mut().zip(points.iter())
         {
-            p.score += p.hand.iter().map(card_score).sum::<i32>();
+            p.score += points;
         }
 
         let standings = self.players.iter().fold(HashMap::new(), |mut map, p| {
@@ -252,6 +392,341 @@ impl Game
 
         if crate::PRINT { println!("Standings: {:?}", standings); }
 
-        self.players.iter().map(|p| p.score).collect::<Vec<i32>>()
+        self.result = Some(HandResult { winner, remaining, points });
     }
-}
\ No newline at end of file
+
+    /// Plays the hand out with each player's strategy.
+    pub fn run(&mut self) -> HandResult
+    {
+        if crate::PRINT { println!("First card is {:?}", self.discard_pile.last().unwrap()); }
+
+        while self.decision().is_some() { self.decide(); }
+
+        self.result.clone().unwrap()
+    }
+}
+
+/// Plays `n_games` independent hands over up to `threads` threads. Game `i`
+/// is dealt from `seed + i`, so results don't depend on the thread count.
+pub fn simulate(player_types: &[StrategyType], rules: &Rules, n_games: usize, seed: u64, threads: usize) -> Vec<HandResult>
+{
+    let threads = threads.clamp(1, n_games.max(1));
+    let per_thread = n_games.div_ceil(threads);
+
+    std::thread::scope(|scope| {
+        let handles : Vec<_> = (0..threads).map(|t| {
+            scope.spawn(move || {
+                (t * per_thread..((t + 1) * per_thread).min(n_games))
+                    .map(|i| Game::with_seed(player_types, rules.clone(), seed.wrapping_add(i as u64)).run())
+                    .collect::<Vec<HandResult>>()
+            })
+        }).collect();
+
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// `opponents` with `player` sat among them in `seat`.
+pub fn seated(player: &StrategyType, opponents: &[StrategyType], seat: usize) -> Vec<StrategyType>
+{
+    let mut players = opponents.to_vec();
+    players.insert(seat, player.clone());
+    players
+}
+
+/// The points `player` scores in each of `n_games` hands against `opponents`,
+/// its seat rotating so it doesn't always go first. As in `simulate`, game
+/// `i` is dealt from `seed + i`, so players scored with the same seed are
+/// dealt the same hands.
+pub fn rotating_points(player: &StrategyType, opponents: &[StrategyType], rules: &Rules, n_games: usize, seed: u64) -> Vec<i32>
+{
+    (0..n_games).map(|i| {
+        let seat = i % (opponents.len() + 1);
+        Game::with_seed(&seated(player, opponents, seat), rules.clone(), seed.wrapping_add(i as u64)).run().points[seat]
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strategy::baseline::RandomStrategy;
+
+    use std::convert::TryFrom;
+
+    fn cards(codes: &[&str]) -> Vec<Card>
+    {
+        codes.iter().map(|c| Card::try_from(*c).unwrap()).collect()
+    }
+
+    /// A game with the given hands, `top` face up and `deck` left to draw
+    /// from (drawn from the end), with seat 0 to play.
+    fn rigged(rules: Rules, hands: &[&[&str]], top: &str, deck: &[&str]) -> Game
+    {
+        let player_types = vec![RandomStrategy::strategy_type("random"); hands.len()];
+        let mut game = Game::with_seed(&player_types, rules, 0);
+
+        for (player, hand) in game.players.iter_mut().zip(hands) {
+            player.hand = cards(hand).into_iter().collect();
+        }
+
+        let top = Card::try_from(top).unwrap();
+
+        game.deck = cards(deck);
+        game.discard_pile = vec![top];
+        game.log.clear();
+        game.record(Turn { player: None, action: Action::First(top) });
+        game.awaiting_suit = false;
+        game.curr_player_id = 0;
+        game
+    }
+
+    fn last_action(game: &Game) -> &Action
+    {
+        &game.log.last().unwrap().action
+    }
+
+    #[test]
+    fn larger_tables_are_dealt_from_more_decks()
+    {
+        for (players, decks) in [(2, 1), (6, 1), (7, 2), (MAX_PLAYERS, 2)] {
+            let player_types = vec![RandomStrategy::strategy_type("random"); players];
+            let game = Game::with_seed(&player_types, Rules::default(), 0);
+
+            let mut dealt : Vec<Card> = game.players.iter().flat_map(|p| p.hand.iter()).collect();
+            dealt.extend(&game.deck);
+            dealt.extend(&game.discard_pile);
+            dealt.sort();
+
+            let expected : Vec<Card> = game.table.rules.deck(decks).iter().collect();
+            assert_eq!(dealt, expected, "{} players", players);
+            assert!(game.players.iter().all(|p| p.hand.len() == STARTING_CARD_COUNT));
+        }
+    }
+
+    #[test]
+    fn drawing_from_an_empty_deck_keeps_the_top_discard()
+    {
+        let mut game = rigged(Rules::default(), &[&["3C"], &["4C"]], "5S", &[]);
+        game.discard_pile = cards(&["6H", "7H", "5S"]);
+
+        let drawn = game.draw();
+
+        assert!(drawn == cards(&["6H"])[0] || drawn == cards(&["7H"])[0]);
+        assert_eq!(game.discard_pile, cards(&["5S"]));
+        assert_eq!(game.cards_available(), 1);
+    }
+
+    #[test]
+    fn penalties_are_capped_at_the_cards_available()
+    {
+        let mut game = rigged(Rules::default(), &[&["3C"], &["2S", "4C"]], "5S", &["9D"]);
+        game.discard_pile = cards(&["6H", "5S"]);
+
+        game.curr_player_id = 1;
+        game.play(cards(&["2S"]));
+        assert_eq!(game.table.penalty, 2);
+
+        game.table.penalty = 5;
+        game.play(Vec::new());
+
+        assert_eq!(last_action(&game), &Action::PickedUp(3));
+        assert_eq!(game.players[0].hand.len(), 4);
+        assert_eq!(game.cards_available(), 0);
+    }
+
+    #[test]
+    fn a_hand_nobody_can_go_in_ends_without_a_winner()
+    {
+        let mut game = rigged(Rules::default(), &[&["3C"], &["4C"], &["6C"]], "5H", &[]);
+
+        for _ in 0..3 {
+            assert_eq!(game.decision(), Some(Decision::Chain));
+            game.play(Vec::new());
+            assert_eq!(last_action(&game), &Action::PickedUp(0));
+        }
+
+        let result = game.result().unwrap();
+        assert_eq!(result.winner, None);
+        assert_eq!(result.remaining.iter().map(|h| h.len()).collect::<Vec<usize>>(), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn going_out_on_a_wild_card_still_nominates()
+    {
+        let mut game = rigged(Rules::default(), &[&["AS"], &["4C"]], "5H", &["9D"]);
+
+        game.play(cards(&["AS"]));
+        assert_eq!(game.decision(), Some(Decision::Suit));
+
+        game.nominate(Suit::Clubs);
+
+        assert_eq!(game.decision(), None);
+        assert_eq!(game.result().unwrap().winner, Some(0));
+    }
+
+    #[test]
+    fn a_wild_card_that_does_not_go_out_nominates()
+    {
+        let mut game = rigged(Rules::default(), &[&["AS", "3C"], &["4C"]], "5H", &["9D"]);
+
+        game.play(cards(&["AS"]));
+        assert_eq!(game.decision(), Some(Decision::Suit));
+
+        game.nominate(Suit::Clubs);
+        assert_eq!(game.curr_player_id, 1);
+        assert_eq!(game.table.nominated, Some(Suit::Clubs));
+    }
+
+    fn stacking(effects: &str) -> Rules
+    {
+        let mut rules = Rules::default();
+        rules.stacking_skips = true;
+        rules.set_effects(effects).unwrap();
+        rules
+    }
+
+    /// The seats that missed a go since the log was `from` long, in order.
+    fn skipped_since(game: &Game, from: usize) -> Vec<usize>
+    {
+        game.log[from..].iter().filter(|t| t.action == Action::Skipped).map(|t| t.player.unwrap()).collect()
+    }
+
+    #[test]
+    fn two_seats_stacked_skips_come_back_round()
+    {
+        let mut game = rigged(stacking(STANDARD_EFFECTS), &[&["8S", "8H", "8D", "3C"], &["4C", "5C"]], "9S", &["9D"]);
+
+        game.play(cards(&["8S", "8H", "8D"]));
+        assert_eq!((game.curr_player_id, game.table.skips), (1, 3));
+
+        let from = game.log.len();
+        game.play(Vec::new());
+
+        // The player who played the skips misses their own next go too.
+        assert_eq!(skipped_since(&game, from), vec![1, 0, 1]);
+        assert_eq!((game.curr_player_id, game.table.skips), (0, 0));
+        assert_eq!(game.decision(), Some(Decision::Chain));
+    }
+
+    #[test]
+    fn without_stacking_a_chain_of_skips_skips_once()
+    {
+        let mut game = rigged(Rules::default(), &[&["8S", "8H", "8D", "3C"], &["4C", "5C"]], "9S", &["9D"]);
+
+        game.play(cards(&["8S", "8H", "8D"]));
+        assert_eq!(game.table.skips, 1);
+
+        let from = game.log.len();
+        game.play(Vec::new());
+
+        assert_eq!(skipped_since(&game, from), vec![1]);
+        assert_eq!(game.curr_player_id, 0);
+    }
+
+    #[test]
+    fn three_seats_skip_the_next_two()
+    {
+        let mut game = rigged(stacking(STANDARD_EFFECTS), &[&["8S", "8H", "3C"], &["4C"], &["5C"]], "9S", &["9D"]);
+
+        game.play(cards(&["8S", "8H"]));
+
+        let from = game.log.len();
+        game.play(Vec::new());
+
+        assert_eq!(skipped_since(&game, from), vec![1, 2]);
+        assert_eq!(game.curr_player_id, 0);
+    }
+
+    #[test]
+    fn four_seats_answered_skips_add_up()
+    {
+        let hands : [&[&str]; 4] = [&["8S", "3C"], &["8C", "4C"], &["5C"], &["6C"]];
+        let mut game = rigged(stacking(STANDARD_EFFECTS), &hands, "9S", &["9D"]);
+
+        game.play(cards(&["8S"]));
+        game.play(cards(&["8C"]));
+        assert_eq!((game.curr_player_id, game.table.skips), (2, 2));
+
+        let from = game.log.len();
+        game.play(Vec::new());
+
+        // Once seat 2 has missed its go, seat 3 can't answer either.
+        assert_eq!(skipped_since(&game, from), vec![2, 3]);
+        assert_eq!(game.curr_player_id, 0);
+    }
+
+    #[test]
+    fn reversed_play_skips_the_other_way()
+    {
+        let mut game = rigged(stacking(STANDARD_EFFECTS), &[&["8S", "8H", "3C"], &["4C"], &["5C"], &["6C"]], "9S", &["9D"]);
+        game.table.reversed = true;
+
+        game.play(cards(&["8S", "8H"]));
+        assert_eq!(game.curr_player_id, 3);
+
+        let from = game.log.len();
+        game.play(Vec::new());
+
+        assert_eq!(skipped_since(&game, from), vec![3, 2]);
+        assert_eq!(game.curr_player_id, 1);
+    }
+
+    #[test]
+    fn a_chain_passes_on_only_what_it_ends_with()
+    {
+        let effects = "8=skip,9=penalty:2";
+
+        // Ending on the penalty: the next player picks up and nobody is skipped.
+        let mut game = rigged(stacking(effects), &[&["8S", "9S", "3C"], &["4C"], &["5C"]], "7S", &["9D", "XD", "JD"]);
+        game.play(cards(&["8S", "9S"]));
+        assert_eq!((game.table.penalty, game.table.skips), (2, 0));
+
+        let from = game.log.len();
+        game.play(Vec::new());
+        assert_eq!(game.log[from].action, Action::PickedUp(2));
+        assert!(skipped_since(&game, from).is_empty());
+        assert_eq!(game.curr_player_id, 2);
+
+        // Ending on the skip: the penalty is dropped and the next player misses a go.
+        let mut game = rigged(stacking(effects), &[&["9S", "8S", "3C"], &["4C"], &["5C"]], "XS", &["9D", "XD", "JD"]);
+        game.play(cards(&["9S", "8S"]));
+        assert_eq!((game.table.penalty, game.table.skips), (0, 1));
+
+        let from = game.log.len();
+        game.play(Vec::new());
+        assert_eq!(skipped_since(&game, from), vec![1]);
+        assert_eq!(game.players[1].hand.len(), 1);
+        assert_eq!(game.curr_player_id, 2);
+    }
+
+    #[test]
+    fn going_out_on_a_penalty_scores_the_bonus()
+    {
+        let mut rules = Rules::default();
+        rules.penalty_finish_bonus = 5;
+
+        let mut game = rigged(rules, &[&["2S"], &["4C"]], "5S", &["9D"]);
+
+        game.play(cards(&["2S"]));
+
+        assert_eq!(game.result().unwrap().points, vec![-5, 4]);
+        assert_eq!(game.players.iter().map(|p| p.score).collect::<Vec<i32>>(), vec![-5, 4]);
+    }
+
+    #[test]
+    fn seeded_games_replay_random_strategies()
+    {
+        let player_types = vec![RandomStrategy::strategy_type("random"); 3];
+
+        let play = |seed: u64| {
+            let mut game = Game::with_seed(&player_types, Rules::default(), seed);
+            game.run();
+            game.log
+        };
+
+        assert_eq!(play(7), play(7));
+        assert_ne!(play(7), play(8));
+    }
+}
+
@@ -0,0 +1,260 @@
+use crate::rules::*;
+use crate::game::*;
+use crate::strategy::*;
+
+/// Clears the terminal and moves the cursor home.
+const CLEAR : &str = "\x1b[2J\x1b[H";
+
+fn is_human(player_type: &StrategyType) -> bool
+{
+    player_type.spec.split(':').next() == Some("human")
+}
+
+fn wait_for_enter()
+{
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// A turn from the log as everyone at the table saw it.
+pub fn describe_turn(game: &Game, turn: &Turn) -> String
+{
+    let name = turn.player.map_or("The dealer", |p| game.players[p].name.as_str());
+
+    match &turn.action {
+        Action::First(card) => format!("{} turns up {:?}.", name, card),
+        Action::Played(chain) => format!("{} plays {:?}.", name, chain),
+        Action::Nominated(suit) => format!("{} nominates {:?}.", name, suit),
+        Action::PickedUp(n) => format!("{} picks up {}.", name, n),
+        Action::Skipped => format!("{} misses a go.", name)
+    }
+}
+
+/// Everything at the table but the hands, with the turns from `since` on in
+/// the log, as seen from `seat`.
+pub fn table_summary(game: &Game, seat: usize, since: usize) -> String
+{
+    let table = &game.table;
+    let mut s = String::new();
+
+    for turn in &game.log[since.min(game.log.len())..] {
+        s += &format!("  {}\n", describe_turn(game, turn));
+    }
+
+    for (i, player) in game.players.iter().enumerate() {
+        let marker = if i == seat { "->" } else { "  " };
+        s += &format!("{} {}: {} cards\n", marker, player.name, player.hand.len());
+    }
+
+    s += &format!("Top card: {:?}", table.top_card);
+
+    if let Some(suit) = table.nominated { s += &format!(", {:?} nominated", suit); }
+    if table.penalty > 0 { s += &format!(", {} to pick up", table.penalty); }
+    if table.skips > 0 { s += &format!(", {} to miss a go", table.skips); }
+    if table.reversed { s += ", play reversed"; }
+
+    s += &format!(". {} cards left to draw.\n", game.cards_available());
+    s
+}
+
+/// What the terminal shows before the human in `seat` makes `decision`,
+/// `showing` being the seat it showed last and `seen` where in the log that
+/// seat last saw up to. With `pass_round`, a new seat first gets a cleared
+/// screen asking for the terminal to be passed on, returned apart so Enter
+/// can be waited for before the rest is shown.
+fn before_turn(game: &Game, seat: usize, decision: Decision, showing: Option<usize>, seen: usize, pass_round: bool) -> (Option<String>, String)
+{
+    let new_seat = showing != Some(seat);
+    let mut shown = String::new();
+
+    let pass = if new_seat && pass_round {
+        shown += CLEAR;
+        Some(format!("{}Pass to {} and press Enter.\n", CLEAR, game.players[seat].name))
+    } else {
+        None
+    };
+
+    if decision == Decision::Chain || new_seat { shown += &table_summary(game, seat, seen); }
+
+    (pass, shown)
+}
+
+/// How the hand ended and the running `totals`, which already count it,
+/// with the turns from `since` on that weren't shown yet.
+fn hand_over(game: &Game, totals: &[i32], since: usize, pass_round: bool) -> String
+{
+    let result = game.result().unwrap();
+    let mut s = if pass_round { CLEAR.to_string() } else { String::new() };
+
+    for turn in &game.log[since.min(game.log.len())..] {
+        s += &format!("  {}\n", describe_turn(game, turn));
+    }
+
+    match result.winner {
+        Some(w) => s += &format!("{} wins the hand.\n", game.players[w].name),
+        None => s += "Nobody can go and the deck is empty.\n"
+    }
+
+    for (i, player) in game.players.iter().enumerate() {
+        s += &format!("{}: {:?} scores {}, {} in total\n", player.name, result.remaining[i], result.points[i], totals[i]);
+    }
+
+    s
+}
+
+/// Plays hands at one terminal for any mix of humans and bots, keeping a
+/// running total. With more than one human, the screen is cleared and the
+/// next human has to press Enter before their hand is shown, so it can be
+/// passed round without anyone seeing another's cards.
+pub fn play(player_types: &[StrategyType], rules: &Rules)
+{
+    let humans : Vec<bool> = player_types.iter().map(is_human).collect();
+    let pass_round = humans.iter().filter(|h| **h).count() > 1;
+
+    let mut totals = vec![0; player_types.len()];
+
+    loop {
+        let mut game = Game::with_rules(player_types, rules.clone());
+        let mut seen = vec![0; player_types.len()];
+        let mut showing = None;
+
+        while let Some(decision) = game.decision() {
+
+            let seat = game.curr_player_id;
+
+            if !humans[seat] { game.decide(); continue; }
+
+            let (pass, shown) = before_turn(&game, seat, decision, showing, seen[seat], pass_round);
+
+            if let Some(pass) = pass {
+                print!("{}", pass);
+                wait_for_enter();
+            }
+
+            print!("{}", shown);
+            game.decide();
+
+            seen[seat] = game.log.len();
+            showing = Some(seat);
+        }
+
+        for (total, points) in totals.iter_mut().zip(&game.result().unwrap().points) { *total += points; }
+
+        print!("{}", hand_over(&game, &totals, showing.map_or(0, |s| seen[s]), pass_round));
+
+        println!("Press Enter for another hand, or type q to stop.");
+
+        let mut input = String::new();
+
+        match std::io::stdin().read_line(&mut input) {
+            Ok(n) if n > 0 && input.trim() != "q" => continue,
+            _ => return
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::cards::*;
+    use crate::strategy::registry::Registry;
+
+    use std::convert::TryFrom;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    fn game(players: usize) -> Game
+    {
+        let bot = Registry::standard().parse("computer_v1").unwrap();
+        Game::with_seed(&vec![bot; players], Rules::default(), 1)
+    }
+
+    #[test]
+    fn only_human_specs_are_human()
+    {
+        let registry = Registry::standard();
+
+        assert!(is_human(&registry.parse("human").unwrap()));
+        assert!(is_human(&registry.parse("human:advisor=random").unwrap()));
+        assert!(!is_human(&registry.parse("computer_v1").unwrap()));
+    }
+
+    #[test]
+    fn turns_as_the_table_saw_them()
+    {
+        let game = game(2);
+        let line = |player, action| describe_turn(&game, &Turn { player, action });
+
+        assert_eq!(line(None, Action::First(card("5S"))), "The dealer turns up ⟨5♠⟩.");
+        assert_eq!(line(Some(1), Action::Played(vec![card("XH"), card("XD")])), "Player 1 (Computer (v1)) plays [⟨X♥⟩, ⟨X♦⟩].");
+        assert_eq!(line(Some(0), Action::Nominated(Suit::Hearts)), "Player 0 (Computer (v1)) nominates ♥.");
+        assert_eq!(line(Some(1), Action::PickedUp(3)), "Player 1 (Computer (v1)) picks up 3.");
+        assert_eq!(line(Some(0), Action::Skipped), "Player 0 (Computer (v1)) misses a go.");
+    }
+
+    #[test]
+    fn the_summary_shows_everything_but_the_hands()
+    {
+        let mut game = game(3);
+        let top = game.table.top_card;
+
+        let summary = table_summary(&game, 1, 0);
+        let lines : Vec<&str> = summary.lines().collect();
+
+        assert_eq!(lines[0], format!("  The dealer turns up {:?}.", top));
+        assert_eq!(lines[1], "   Player 0 (Computer (v1)): 7 cards");
+        assert_eq!(lines[2], "-> Player 1 (Computer (v1)): 7 cards");
+        assert_eq!(lines[3], "   Player 2 (Computer (v1)): 7 cards");
+        assert!(lines[4].starts_with(&format!("Top card: {:?}", top)));
+        assert!(!table_summary(&game, 1, game.log.len()).contains("turns up"));
+
+        game.table.nominated = Some(Suit::Hearts);
+        game.table.penalty = 2;
+        game.table.skips = 1;
+        game.table.reversed = true;
+
+        let last = table_summary(&game, 0, 1).lines().last().unwrap().to_string();
+        assert_eq!(last, format!("Top card: {:?}, ♥ nominated, 2 to pick up, 1 to miss a go, play reversed. {} cards left to draw.", top, game.cards_available()));
+    }
+
+    #[test]
+    fn the_terminal_is_cleared_and_passed_on_only_between_humans()
+    {
+        let game = game(2);
+        let summary = table_summary(&game, 1, 0);
+
+        let (pass, shown) = before_turn(&game, 1, Decision::Chain, Some(0), 0, true);
+        assert_eq!(pass, Some(format!("{}Pass to Player 1 (Computer (v1)) and press Enter.\n", CLEAR)));
+        assert_eq!(shown, format!("{}{}", CLEAR, summary));
+
+        assert!(before_turn(&game, 1, Decision::Chain, None, 0, true).0.is_some());
+        assert_eq!(before_turn(&game, 1, Decision::Chain, Some(1), 0, true), (None, summary.clone()));
+        assert_eq!(before_turn(&game, 1, Decision::Suit, Some(1), 0, true), (None, String::new()));
+        assert_eq!(before_turn(&game, 1, Decision::Suit, Some(0), 0, false), (None, summary.clone()));
+        assert_eq!(before_turn(&game, 1, Decision::Chain, Some(0), 0, false), (None, summary));
+    }
+
+    #[test]
+    fn the_hand_ends_with_the_unseen_turns_and_the_totals()
+    {
+        let mut game = game(2);
+        game.run();
+
+        let since = game.log.len() - 1;
+        let report = hand_over(&game, &[40, 7], since, false);
+        let lines : Vec<&str> = report.lines().collect();
+        let result = game.result().unwrap();
+
+        assert_eq!(lines[0], format!("  {}", describe_turn(&game, game.log.last().unwrap())));
+        assert!(lines[1].ends_with("wins the hand.") || lines[1] == "Nobody can go and the deck is empty.");
+        assert_eq!(lines[2], format!("Player 0 (Computer (v1)): {:?} scores {}, 40 in total", result.remaining[0], result.points[0]));
+        assert_eq!(lines[3], format!("Player 1 (Computer (v1)): {:?} scores {}, 7 in total", result.remaining[1], result.points[1]));
+        assert_eq!(lines.len(), 4);
+
+        assert!(hand_over(&game, &[40, 7], since, true).starts_with(CLEAR));
+        assert!(!report.contains(CLEAR));
+    }
+}
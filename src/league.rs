@@ -0,0 +1,408 @@
+use rand::{Rng, thread_rng};
+use rand::seq::SliceRandom;
+
+use crate::rules::*;
+use crate::evolve::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::heuristic::*;
+use crate::strategy::registry::*;
+
+pub const INITIAL_ELO : f64 = 1500.0;
+
+/// Elo points at stake between each pair of players in a hand, shared out
+/// over the other strategies at the table. Kept low as single hands are noisy.
+const K_FACTOR : f64 = 4.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rating
+{
+    pub name: String,
+    pub elo: f64,
+    pub games: usize
+}
+
+/// Elo ratings for strategies playing multi-player hands, each scored as a
+/// match against every other player at the table.
+///
+/// Saved as one `elo games name` line per strategy.
+#[derive(Clone, Debug, Default)]
+pub struct Ratings
+{
+    pub entries: Vec<Rating>
+}
+
+impl Ratings
+{
+    /// Reads a ratings table, starting an empty one if the file is missing.
+    pub fn load(path: &str) -> Result<Ratings, String>
+    {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Ratings::default()),
+            Err(e) => return Err(format!("Could not read {}: {}", path, e))
+        };
+
+        let mut ratings = Ratings::default();
+
+        for line in text.lines().map(|l| l.split('#').next().unwrap().trim()).filter(|l| !l.is_empty()) {
+
+            let fields : Vec<&str> = line.split_whitespace().collect();
+
+            match fields[..] {
+                [elo, games, name] => ratings.entries.push(Rating {
+                    name: name.to_string(),
+                    elo: elo.parse().map_err(|_| format!("Invalid rating '{}'", elo))?,
+                    games: games.parse().map_err(|_| format!("Invalid game count '{}'", games))?
+                }),
+                _ => return Err(format!("Expected 'elo games name', found '{}'", line))
+            }
+        }
+
+        Ok(ratings)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String>
+    {
+        let mut text = String::from("# elo games name\n");
+
+        for r in &self.entries {
+            check_name(&r.name)?;
+            text += &format!("{:.1} {} {}\n", r.elo, r.games, r.name);
+        }
+
+        std::fs::write(path, text).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    pub fn elo(&self, name: &str) -> f64
+    {
+        self.entries.iter().find(|r| r.name == name).map_or(INITIAL_ELO, |r| r.elo)
+    }
+
+    fn entry(&mut self, name: &str) -> &mut Rating
+    {
+        match self.entries.iter().position(|r| r.name == name) {
+            Some(i) => &mut self.entries[i],
+            None => {
+                self.entries.push(Rating { name: name.to_string(), elo: INITIAL_ELO, games: 0 });
+                self.entries.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Updates the ratings after one hand, given each seat's strategy and
+    /// points. Seats sharing a strategy aren't rated against each other, and
+    /// a strategy in several seats takes the average of their changes and
+    /// plays one game, so its rating moves no faster than anyone else's.
+    pub fn record(&mut self, names: &[&str], points: &[i32], lower_is_better: bool)
+    {
+        let n = names.len();
+        let elos : Vec<f64> = names.iter().map(|name| self.elo(name)).collect();
+        let mut deltas = vec![0.0; n];
+
+        for i in 0..n {
+            let opponents = names.iter().filter(|name| **name != names[i]).count();
+
+            for j in 0..n {
+                if names[i] == names[j] { continue; }
+
+                let expected = 1.0 / (1.0 + 10f64.powf((elos[j] - elos[i]) / 400.0));
+
+                let actual = match points[i].cmp(&points[j]) {
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Less => if lower_is_better { 1.0 } else { 0.0 },
+                    std::cmp::Ordering::Greater => if lower_is_better { 0.0 } else { 1.0 }
+                };
+
+                deltas[i] += K_FACTOR / opponents as f64 * (actual - expected);
+            }
+        }
+
+        let mut distinct : Vec<&str> = names.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+
+        for name in distinct {
+            let seats : Vec<f64> = (0..n).filter(|i| names[*i] == name).map(|i| deltas[i]).collect();
+
+            let entry = self.entry(name);
+            entry.elo += seats.iter().sum::<f64>() / seats.len() as f64;
+            entry.games += 1;
+        }
+    }
+
+    /// The table, best first.
+    pub fn leaderboard(&self) -> String
+    {
+        let mut entries : Vec<&Rating> = self.entries.iter().collect();
+        entries.sort_by(|a, b| b.elo.partial_cmp(&a.elo).unwrap());
+
+        entries
+            .iter()
+            .map(|r| format!("{:>7.1} {:>8} {}\n", r.elo, r.games, r.name))
+            .collect()
+    }
+}
+
+/// Names are saved as single words, so they can't be empty or hold spaces
+/// or the `#` that starts a comment.
+fn check_name(name: &str) -> Result<(), String>
+{
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '#') {
+        return Err(format!("'{}' can't be used as a name: names are single words without '#'", name));
+    }
+
+    Ok(())
+}
+
+/// A named strategy in the pool.
+#[derive(Clone, Debug)]
+pub struct Entrant
+{
+    pub name: String,
+    pub strategy: StrategyType
+}
+
+/// Plays `games` hands of `seats` players, updating the ratings after each.
+/// `entrant` sits in every hand, taking each seat in turn, and the other
+/// seats are drawn at random from `pool`.
+pub fn play_rated(entrant: &Entrant, pool: &[Entrant], ratings: &mut Ratings, games: usize, seats: usize, rules: &Rules)
+{
+    let mut rng = thread_rng();
+
+    for game in 0..games {
+
+        let mut table : Vec<&Entrant> = (1..seats).map(|_| pool.choose(&mut rng).unwrap()).collect();
+        table.insert(game % seats, entrant);
+
+        let player_types : Vec<StrategyType> = table.iter().map(|e| e.strategy.clone()).collect();
+        let names : Vec<&str> = table.iter().map(|e| e.name.as_str()).collect();
+
+        let result = Game::with_rules(&player_types, rules.clone()).run();
+
+        ratings.record(&names, &result.points, rules.scoring.lower_is_better());
+    }
+}
+
+pub struct TrainingConfig
+{
+    pub rounds: usize,
+    pub baselines: Vec<&'static str>,
+    pub checkpoints_path: String,
+    pub ratings_path: String,
+    pub rating_games: usize,
+    pub seats: usize,
+    pub rules: Rules,
+    pub evolution: EvolutionConfig
+}
+
+impl Default for TrainingConfig
+{
+    fn default() -> TrainingConfig
+    {
+        TrainingConfig
+        {
+            rounds: 5,
+            baselines: vec!["computer_v1", "computer_v2"],
+            checkpoints_path: "checkpoints.txt".to_string(),
+            ratings_path: "ratings.txt".to_string(),
+            rating_games: 5_000,
+            seats: 4,
+            rules: Rules::default(),
+            evolution: EvolutionConfig { generations: 5, ..EvolutionConfig::default() }
+        }
+    }
+}
+
+/// Reads checkpoints saved as `spec # name` lines.
+fn load_checkpoints(path: &str, registry: &Registry) -> Result<Vec<Entrant>, String>
+{
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Could not read {}: {}", path, e))
+    };
+
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| match l.split_once('#') {
+            Some((spec, name)) => {
+                check_name(name.trim())?;
+                Ok(Entrant { name: name.trim().to_string(), strategy: registry.parse(spec)? })
+            },
+            None => Err(format!("Checkpoint '{}' has no name", l))
+        })
+        .collect()
+}
+
+/// What `train` reports as it goes.
+pub enum TrainingEvent<'a>
+{
+    /// A generation of the round's evolution: its number and the best and
+    /// median average scores.
+    Generation(usize, f64, f64),
+    /// A round's new checkpoint, the average score it was evolved to, the
+    /// opponents it was evolved against and the ratings after its games.
+    Round { round: usize, name: &'a str, fitness: f64, against: Vec<&'a str>, ratings: &'a Ratings }
+}
+
+/// Self-play: each round evolves a new heuristic from the latest checkpoint
+/// against opponents drawn from the pool of baselines and past checkpoints,
+/// saves it as the next checkpoint and rates it against the pool.
+/// Checkpoints and ratings persist between runs.
+pub fn train<F>(config: &TrainingConfig, mut progress: F) -> Result<Ratings, String>
+    where F: FnMut(TrainingEvent)
+{
+    let registry = Registry::standard();
+    let mut rng = thread_rng();
+
+    let mut checkpoints = load_checkpoints(&config.checkpoints_path, &registry)?;
+    let mut ratings = Ratings::load(&config.ratings_path)?;
+
+    let baselines = config.baselines
+                    .iter()
+                    .map(|spec| Ok(Entrant { name: spec.to_string(), strategy: registry.parse(spec)? }))
+                    .collect::<Result<Vec<Entrant>, String>>()?;
+
+    for round in 0..config.rounds {
+
+        let start = match checkpoints.last() {
+            Some(c) => HeuristicParams::from_spec(&c.strategy.spec)?,
+            None => HeuristicParams::default()
+        };
+
+        let pool : Vec<Entrant> = baselines.iter().chain(checkpoints.iter()).cloned().collect();
+
+        let opponents : Vec<&Entrant> = (1..config.seats).map(|_| pool.choose(&mut rng).unwrap()).collect();
+
+        let evolution = EvolutionConfig {
+            start,
+            opponents: opponents.iter().map(|e| e.strategy.clone()).collect(),
+            rules: config.rules.clone(),
+            seed: rng.gen(),
+            ..config.evolution.clone()
+        };
+
+        let (best, fitness) = evolve(&evolution, |generation, best, median| {
+            progress(TrainingEvent::Generation(generation, best, median));
+        })?;
+
+        let entrant = Entrant { name: format!("v{}", checkpoints.len() + 1), strategy: registry.parse(&best.to_spec())? };
+
+        let text : String = checkpoints.iter().chain(Some(&entrant)).map(|c| format!("{} # {}\n", c.strategy.spec, c.name)).collect();
+        std::fs::write(&config.checkpoints_path, text)
+            .map_err(|e| format!("Could not write {}: {}", config.checkpoints_path, e))?;
+
+        play_rated(&entrant, &pool, &mut ratings, config.rating_games, config.seats, &config.rules);
+        ratings.save(&config.ratings_path)?;
+
+        progress(TrainingEvent::Round {
+            round,
+            name: &entrant.name,
+            fitness,
+            against: opponents.iter().map(|e| e.name.as_str()).collect(),
+            ratings: &ratings
+        });
+
+        checkpoints.push(entrant);
+    }
+
+    Ok(ratings)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn the_entrant_sits_in_every_rated_hand()
+    {
+        let registry = Registry::standard();
+        let entrant = |name: &str| Entrant { name: name.to_string(), strategy: registry.parse(name).unwrap() };
+
+        let pool = vec![entrant("computer_v1"), entrant("computer_v2")];
+        let mut ratings = Ratings::default();
+
+        play_rated(&entrant("defender"), &pool, &mut ratings, 30, 3, &Rules::default());
+
+        let games = |name: &str| ratings.entries.iter().find(|r| r.name == name).map_or(0, |r| r.games);
+
+        assert_eq!(games("defender"), 30);
+
+        // Two seats a hand, but a strategy in both plays one game.
+        let others = games("computer_v1") + games("computer_v2");
+        assert!((30..=60).contains(&others), "{} games", others);
+    }
+
+    #[test]
+    fn elo_follows_the_expected_score()
+    {
+        let mut ratings = Ratings::default();
+
+        ratings.record(&["a", "b"], &[0, 10], true);
+        assert!((ratings.elo("a") - (INITIAL_ELO + K_FACTOR / 2.0)).abs() < 1e-9);
+        assert!((ratings.elo("b") - (INITIAL_ELO - K_FACTOR / 2.0)).abs() < 1e-9);
+
+        // Now a is expected to win, so beating b again gains less.
+        let expected = 1.0 / (1.0 + 10f64.powf(-K_FACTOR / 400.0));
+        ratings.record(&["a", "b"], &[0, 10], true);
+        assert!((ratings.elo("a") - (INITIAL_ELO + K_FACTOR / 2.0 + K_FACTOR * (1.0 - expected))).abs() < 1e-9);
+
+        // Higher is better under other scoring, and a draw between equals changes nothing.
+        let mut ratings = Ratings::default();
+        ratings.record(&["a", "b", "c"], &[10, 0, 10], false);
+        assert!(ratings.elo("b") < INITIAL_ELO);
+        assert_eq!(ratings.elo("a"), ratings.elo("c"));
+    }
+
+    #[test]
+    fn a_strategy_in_two_seats_moves_as_fast_as_one_in_one()
+    {
+        let mut ratings = Ratings::default();
+        ratings.record(&["a", "a", "b"], &[0, 0, 10], true);
+
+        let mut single = Ratings::default();
+        single.record(&["a", "b"], &[0, 10], true);
+
+        assert!((ratings.elo("a") - single.elo("a")).abs() < 1e-9);
+        assert_eq!(ratings.entries.iter().map(|r| r.games).collect::<Vec<usize>>(), vec![1, 1]);
+    }
+
+    #[test]
+    fn ratings_survive_a_round_trip()
+    {
+        let mut ratings = Ratings::default();
+        ratings.record(&["v1", "computer_v1", "heuristic:pickup_weight=12"], &[0, 5, 10], true);
+
+        let path = std::env::temp_dir().join(format!("ratings-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        ratings.save(path).unwrap();
+        let loaded = Ratings::load(path);
+        std::fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.entries.len(), 3);
+
+        for (a, b) in ratings.entries.iter().zip(&loaded.entries) {
+            assert_eq!((&a.name, a.games), (&b.name, b.games));
+            assert!((a.elo - b.elo).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn names_must_be_single_words()
+    {
+        let mut ratings = Ratings::default();
+        ratings.record(&["two words", "b"], &[0, 10], true);
+
+        let path = std::env::temp_dir().join(format!("ratings-bad-{}.txt", std::process::id()));
+        assert!(ratings.save(path.to_str().unwrap()).is_err());
+        assert!(!path.exists());
+
+        assert!(check_name("v3").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("a#b").is_err());
+    }
+}
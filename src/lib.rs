@@ -1,41 +1,26 @@
-use pyo3::prelude::*;
-use pyo3::types::PyType;
+//! The game engine: cards, rules, games and the strategies that play them.
+//! Build with the `python` feature for the `blackjack` Python module.
 
-mod cards;
-mod player;
-mod game;
-mod strategy;
-mod blackjack;
+pub mod cards;
+pub mod player;
+pub mod game;
+pub mod strategy;
+pub mod rules;
+pub mod env;
+pub mod evolve;
+pub mod exploit;
+pub mod hotseat;
+pub mod league;
+pub mod server;
+pub mod client;
 
-use crate::game::*;
-use crate::strategy::*;
+#[cfg(feature = "python")]
+mod python;
 
-const PRINT : bool = false;
+pub use crate::rules::{Effect, Rules, Scoring, TableState};
+pub use crate::cards::{Card, CardSet, Colour, Rank, Suit};
+pub use crate::game::{Action, Decision, Game, HandResult, Turn};
+pub use crate::strategy::{Strategy, StrategyType};
 
-#[pymodule]
-fn blackjack(py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_class::<GameWrapper>()?;
-    Ok(())
-}
-
-#[pyclass]
-#[text_signature = "(g, /)"]
-struct GameWrapper {
-    g: Game
-}
-
-#[pymethods]
-impl GameWrapper {
-    
-    #[new]
-    fn new(c: i32) -> Self {
-        Self {
-            g: Game::new(&vec![StrategyType::ComputerV1; c as usize])
-        }
-    }
-    
-    #[text_signature = "($self)"]
-    fn run(&mut self) {
-        self.g.run();
-    }
-}
\ No newline at end of file
+/// Prints every move as games are played.
+pub const PRINT : bool = false;
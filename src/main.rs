@@ -1,47 +1,268 @@
-mod cards;
-mod player;
-mod game;
-mod strategy;
-mod blackjack;
+use blackjack::game::*;
+use blackjack::rules::*;
+use blackjack::strategy::*;
+use blackjack::strategy::registry::*;
+use blackjack::evolve::*;
+use blackjack::league::*;
+use blackjack::exploit::*;
+use blackjack::hotseat;
+use blackjack::server::*;
+use blackjack::client;
 
-use game::*;
-use strategy::*;
+use std::net::TcpListener;
 
-const PRINT : bool = false;
-
-const NUM_PLAYERS : usize = 4;
 const NUM_GAMES : usize = 30_000;
 
+const USAGE : &str = "Usage: q_learning [--jokers=<per deck>] [--joker-penalty=<cards>] [--effects=<list>] \
+                       [--stacking-skips=<true|false>] [--scoring=<classic|winner-takes-all|wins>] \
+                       [--penalty-finish-bonus=<points>] [bench <strategy>... | play <strategy>... | config <file> | list | evolve | train [rounds] | cfr [iterations] | exploit <strategy> [opponent...] | serve [address] [bot] | connect [address] [name]]";
+
+/// Splits `--name=value` rule options from the rest of the arguments.
+fn parse_rules(args: Vec<String>) -> Result<(Rules, Vec<String>), String>
+{
+    let mut rules = Rules::default();
+    let mut rest = Vec::new();
+
+    for arg in args {
+        let (name, value) = match arg.strip_prefix("--").and_then(|a| a.split_once('=')) {
+            Some(option) => option,
+            None => { rest.push(arg); continue; }
+        };
+
+        let number = || value.parse::<usize>().map_err(|_| format!("{} is not a number", value));
+
+        match name {
+            "jokers" => rules.jokers_per_deck = number()?,
+            "joker-penalty" => rules.joker_penalty = number()?,
+            "effects" => rules.set_effects(value)?,
+            "scoring" => rules.scoring = Scoring::parse(value)?,
+            "penalty-finish-bonus" => rules.penalty_finish_bonus = value.parse().map_err(|_| format!("{} is not a number", value))?,
+            "stacking-skips" => rules.stacking_skips = value.parse().map_err(|_| format!("{} is not true or false", value))?,
+            _ => return Err(format!("Unknown option '--{}'\n{}", name, USAGE))
+        }
+    }
+
+    rules.validate()?;
+    Ok((rules, rest))
+}
+
 fn main() {
 
-    let mut scores = vec![0; NUM_PLAYERS];
+    let (rules, args) = match parse_rules(std::env::args().collect()) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let registry = Registry::standard();
 
-    let now = std::time::Instant::now();
+    let player_types : Result<Vec<StrategyType>, String> = match args.get(1).map(|a| a.as_str()) {
+        None => ["computer_v1", "computer_v1", "computer_v2", "computer_v1"]
+                .iter()
+                .map(|s| registry.parse(s))
+                .collect(),
+        Some("bench") => args[2..].iter().map(|s| registry.parse(s)).collect(),
+        Some("play") => {
+            let parsed : Result<Vec<StrategyType>, String> = args[2..].iter().map(|s| registry.parse(s)).collect();
+
+            match parsed {
+                Ok(strategies) if (2..=MAX_PLAYERS).contains(&strategies.len()) => hotseat::play(&strategies, &rules),
+                Ok(_) => { eprintln!("Play needs 2 to {} players\n{}", MAX_PLAYERS, USAGE); std::process::exit(1); },
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            }
+            return;
+        },
+        Some("config") => match args.get(2) {
+            Some(path) => registry.parse_file(path),
+            None => Err(USAGE.to_string())
+        },
+        Some("list") => {
+            print!("{}", registry.describe());
+            return;
+        },
+        Some("evolve") => {
+            run_evolution(&rules);
+            return;
+        },
+        Some("train") => {
+            let rounds = args.get(2).map(|r| r.parse::<usize>().map_err(|_| format!("{} is not a number", r)));
+
+            let config = match rounds {
+                None => TrainingConfig { rules, ..TrainingConfig::default() },
+                Some(Ok(rounds)) => TrainingConfig { rounds, rules, ..TrainingConfig::default() },
+                Some(Err(e)) => { eprintln!("{}", e); std::process::exit(1); }
+            };
 
-    let player_types = 
-    [
-        StrategyType::ComputerV1,
-        StrategyType::ComputerV1,
-        StrategyType::ComputerV2,
-        StrategyType::ComputerV1
-    ];
+            let trained = train(&config, |event| match event {
+                TrainingEvent::Generation(generation, best, median) => {
+                    println!("Generation {}: best {:.2}, median {:.2}", generation, best, median);
+                },
+                TrainingEvent::Round { round, name, fitness, against, ratings } => {
+                    println!("Round {}: {} averages {:.2} against {}", round, name, fitness, against.join(", "));
+                    print!("{}", ratings.leaderboard());
+                }
+            });
+
+            if let Err(e) = trained {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        },
+        Some("cfr") => {
+            match args.get(2).map(|n| n.parse::<usize>()) {
+                None => run_cfr(1_000_000, &rules),
+                Some(Ok(iterations)) => run_cfr(iterations, &rules),
+                Some(Err(_)) => { eprintln!("{}", USAGE); std::process::exit(1); }
+            }
+            return;
+        },
+        Some("exploit") => {
+            let parsed : Result<Vec<StrategyType>, String> = args[2..].iter().map(|s| registry.parse(s)).collect();
+
+            match parsed {
+                Ok(strategies) if !strategies.is_empty() => run_exploit(&strategies, &rules),
+                Ok(_) => { eprintln!("{}", USAGE); std::process::exit(1); },
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            }
+            return;
+        },
+        Some("serve") => {
+            let address = args.get(2).map_or(DEFAULT_ADDRESS, |a| a.as_str());
+
+            let served = registry.parse(args.get(3).map_or("computer_v1", |b| b.as_str())).and_then(|bot| {
+                let listener = TcpListener::bind(address).map_err(|e| format!("Could not listen on {}: {}", address, e))?;
+
+                println!("Listening on {}", address);
+                Server::new(bot, rules).serve(listener, |e| eprintln!("{}", e));
+                Ok(())
+            });
+
+            if let Err(e) = served { eprintln!("{}", e); std::process::exit(1); }
+            return;
+        },
+        Some("connect") => {
+            let address = args.get(2).map_or(DEFAULT_ADDRESS, |a| a.as_str());
+
+            if let Err(e) = client::connect(address, args.get(3).map(|n| n.as_str())) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        },
+        Some(_) => Err(USAGE.to_string())
+    };
+
+    match player_types {
+        Ok(player_types) if (2..=MAX_PLAYERS).contains(&player_types.len()) => run_benchmark(&player_types, &rules),
+        Ok(_) => { eprintln!("A benchmark needs 2 to {} players\n{}", MAX_PLAYERS, USAGE); std::process::exit(1); },
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_benchmark(player_types: &[StrategyType], rules: &Rules) {
+
+    let mut scores = vec![0; player_types.len()];
+    let mut wins = vec![0; player_types.len()];
+
+    let now = std::time::Instant::now();
 
     for _ in 0..NUM_GAMES
     {
-        let round_scores = Game::new(&player_types).run();
+        let result = Game::with_rules(player_types, rules.clone()).run();
 
-        for i in 0..NUM_PLAYERS {
-            scores[i] += round_scores[i];
+        for (total, s) in scores.iter_mut().zip(result.points) {
+            *total += s;
         }
+
+        if let Some(w) = result.winner { wins[w] += 1; }
     }
-    
+
     println!("Games finished in {:?}", now.elapsed());
-    
-    let avg_scores : Vec<f64> = 
+
+    let avg_scores : Vec<f64> =
                 scores
                 .iter()
                 .map(|s| (*s as f64) / (NUM_GAMES as f64))
                 .collect();
 
-    println!("Average scores {:?}", avg_scores);
-}
\ No newline at end of file
+    println!("Players {:?}", player_types);
+    println!("Average scores {:?} ({} is better)", avg_scores, if rules.scoring.lower_is_better() { "lower" } else { "higher" });
+    println!("Wins {:?}", wins);
+}
+
+fn run_evolution(rules: &Rules) {
+
+    let now = std::time::Instant::now();
+
+    let config = EvolutionConfig { rules: rules.clone(), seed: rand::random(), ..EvolutionConfig::default() };
+
+    let evolved = evolve(&config, |generation, best, median| {
+        println!("Generation {}: best {:.2}, median {:.2}", generation, best, median);
+    });
+
+    let (best, fitness) = match evolved {
+        Ok(evolved) => evolved,
+        Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+    };
+
+    println!("Evolution finished in {:?}", now.elapsed());
+    println!("Best average score {:.2}", fitness);
+    println!("Best parameters {}", best.to_spec());
+}
+
+fn run_cfr(iterations: usize, rules: &Rules) {
+
+    use blackjack::strategy::cfr::*;
+
+    const POLICY_PATH : &str = "cfr.txt";
+    const REPORT_EVERY : usize = 200_000;
+
+    let mut trainer = CfrTrainer::new(rules.clone(), rand::random());
+
+    let now = std::time::Instant::now();
+
+    while trainer.iterations < iterations {
+
+        trainer.iterate();
+
+        if trainer.iterations.is_multiple_of(REPORT_EVERY) || trainer.iterations == iterations {
+
+            let policy = trainer.policy();
+
+            if let Err(e) = policy.save(POLICY_PATH) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+
+            let policy = CfrStrategy::with_policy("cfr", policy);
+            let exploitation = exploit(&policy, std::slice::from_ref(&policy), rules, &ResponseConfig::default());
+
+            println!("{} iterations in {:?}: {} information sets, best response gains {:.2} points a hand",
+                trainer.iterations, now.elapsed(), trainer.size(), exploitation.gain);
+        }
+    }
+}
+
+/// Seats a best response against `strategies[1..]`, or against a copy of
+/// `strategies[0]` if there are no others, and compares it with
+/// `strategies[0]` in the same seats.
+fn run_exploit(strategies: &[StrategyType], rules: &Rules) {
+
+    let reference = &strategies[0];
+    let opponents = if strategies.len() > 1 { &strategies[1..] } else { strategies };
+
+    let now = std::time::Instant::now();
+    let result = exploit(reference, opponents, rules, &ResponseConfig { seed: rand::random(), ..ResponseConfig::default() });
+
+    println!("Finished in {:?}", now.elapsed());
+    println!("Opponents {:?}", opponents);
+    println!("{:?} averages {:.2} ± {:.2}", reference, result.reference.mean, result.reference.std_error);
+    println!("Best response averages {:.2} ± {:.2}", result.response.mean, result.response.std_error);
+    println!("Best response gains {:.2} points a hand", result.gain);
+}
@@ -1,5 +1,4 @@
-use std::collections::HashSet;
-
+use crate::rules::*;
 use crate::cards::*;
 use crate::game::*;
 use crate::strategy::*;
@@ -7,7 +6,7 @@ use crate::strategy::*;
 pub struct Player
 {
     pub name: String,
-    pub hand: HashSet<Card>,
+    pub hand: CardSet,
     pub score: i32,
     pub strategy: Box<dyn Strategy + Send>
 }
@@ -30,15 +29,15 @@ impl Player
         Player 
         {
             name,
-            hand: HashSet::<Card>::new(),
+            hand: CardSet::new(),
             score: 0,
             strategy
         }
     }
 
-    pub fn choose_next(&mut self, log: &[Turn]) -> Vec<Card>
+    pub fn choose_next(&mut self, table: &TableState, log: &[Turn]) -> Vec<Card>
     {
-        self.strategy.choose_next(&self.hand, log)
+        self.strategy.choose_next(&self.hand, table, log)
     }
 
     pub fn choose_suit(&mut self, log: &[Turn]) -> Suit
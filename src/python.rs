@@ -0,0 +1,828 @@
+use pyo3::prelude::*;
+use pyo3::basic::CompareOp;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyBytes;
+use pyo3::wrap_pyfunction;
+use pyo3::PyObjectProtocol;
+
+use crate::rules::*;
+use crate::cards::*;
+use crate::env::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::human::*;
+use crate::strategy::registry::*;
+
+use std::collections::hash_map::DefaultHasher;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+#[pymodule]
+fn blackjack(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<GameWrapper>()?;
+    m.add_class::<PySuit>()?;
+    m.add_class::<PyRank>()?;
+    m.add_class::<PyCard>()?;
+    m.add_class::<PyAction>()?;
+    m.add_class::<PyTurn>()?;
+    m.add_class::<PyRules>()?;
+    m.add_class::<PyHandResult>()?;
+    m.add_class::<PyVecEnv>()?;
+    m.add_function(wrap_pyfunction!(list_strategies, m)?)?;
+    m.add_function(wrap_pyfunction!(simulate, m)?)?;
+    Ok(())
+}
+
+/// Returns `(key, description, parameters)` for every registered strategy.
+#[pyfunction]
+#[text_signature = "()"]
+fn list_strategies() -> Vec<(String, String, Vec<String>)> {
+    Registry::standard()
+        .entries()
+        .iter()
+        .map(|r| (r.key.to_string(),
+                  r.description.to_string(),
+                  r.parameters.iter().map(|p| p.to_string()).collect()))
+        .collect()
+}
+
+/// Plays `n_games` hands between registry specs in Rust threads with the
+/// GIL released. Returns `(scores, winners)` as memoryviews for
+/// `numpy.asarray`: an `(n_games, seats)` int32 array of each game's points
+/// by seat, and an int64 array of each game's winning seat, or -1 if nobody
+/// went out. The same `seed` plays the same games, random strategies
+/// included; `threads` defaults to one per core.
+#[pyfunction(seed = "None", threads = "None", rules = "None")]
+#[text_signature = "(strategies, n_games, seed=None, threads=None, rules=None)"]
+fn simulate(py: Python, strategies: Vec<String>, n_games: usize, seed: Option<u64>, threads: Option<usize>,
+            rules: Option<PyRules>) -> PyResult<(PyObject, PyObject)> {
+
+    let registry = Registry::standard();
+
+    let player_types = strategies
+                        .iter()
+                        .map(|spec| registry.parse(spec))
+                        .collect::<Result<Vec<StrategyType>, String>>()
+                        .map_err(PyValueError::new_err)?;
+
+    if player_types.len() < 2 || player_types.len() > MAX_PLAYERS {
+        return Err(PyValueError::new_err(format!("Between 2 and {} strategies are needed", MAX_PLAYERS)));
+    }
+    if n_games == 0 { return Err(PyValueError::new_err("n_games must be at least 1")); }
+
+    let rules = rules.map(|r| r.rules).unwrap_or_default();
+    let seed = seed.unwrap_or_else(rand::random);
+    let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+
+    let results = py.allow_threads(|| crate::game::simulate(&player_types, &rules, n_games, seed, threads));
+
+    let scores : Vec<i32> = results.iter().flat_map(|r| r.points.iter().copied()).collect();
+    let winners : Vec<i64> = results.iter().map(|r| r.winner.map_or(-1, |w| w as i64)).collect();
+
+    Ok((array(py, &scores, |x| x.to_ne_bytes(), "i", vec![n_games, player_types.len()])?,
+        array(py, &winners, |x| x.to_ne_bytes(), "q", vec![n_games])?))
+}
+
+#[pyclass]
+#[text_signature = "(c, strategies=None, rules=None)"]
+struct GameWrapper {
+    g: Game,
+    errors: Errors
+}
+
+#[pymethods]
+impl GameWrapper {
+    
+    /// `c` players, from 2 to `MAX_PLAYERS`. `strategies`, if given, has one
+    /// entry per seat: a registry spec such as `"heuristic:pickup_weight=12"`,
+    /// a Python object implementing `choose_next` and `choose_suit`, or
+    /// `None` for `computer_v1`. `rules` defaults to the standard game.
+    #[new]
+    #[args(strategies = "None", rules = "None")]
+    fn new(py: Python, c: usize, strategies: Option<Vec<PyObject>>, rules: Option<PyRules>) -> PyResult<Self> {
+
+        if !(2..=MAX_PLAYERS).contains(&c) {
+            return Err(PyValueError::new_err(format!("{} players is outside 2 to {}", c, MAX_PLAYERS)));
+        }
+
+        let rules = rules.map(|r| r.rules).unwrap_or_default();
+        let registry = Registry::standard();
+        let default = registry.parse("computer_v1").unwrap();
+        let errors = Errors::default();
+
+        let strategies = match strategies {
+            None => return Ok(Self { g: Game::with_rules(&vec![default; c], rules), errors }),
+            Some(s) if s.len() != c => {
+                return Err(PyValueError::new_err("strategies must have one entry per player"));
+            },
+            Some(s) => s
+        };
+
+        let mut player_types = Vec::new();
+
+        for obj in &strategies {
+            if obj.is_none(py) {
+                player_types.push(Some(default.clone()));
+            } else if let Ok(spec) = obj.extract::<String>(py) {
+                player_types.push(Some(registry.parse(&spec).map_err(PyValueError::new_err)?));
+            } else {
+                player_types.push(None);
+            }
+        }
+
+        let seated : Vec<StrategyType> = player_types
+                .iter()
+                .map(|pt| pt.clone().unwrap_or_else(|| default.clone()))
+                .collect();
+
+        let strategies = strategies
+                .into_iter()
+                .zip(player_types.iter())
+                .map(|(obj, pt)| -> Box<dyn Strategy + Send> {
+                    match pt {
+                        Some(pt) => make_strategy(pt, &seated),
+                        None => Box::new(PythonStrategy::new(py, obj, errors.clone()))
+                    }
+                })
+                .collect();
+
+        Ok(Self { g: Game::with_strategies(strategies, rules), errors })
+    }
+    
+    /// Plays the hand out and returns how it ended. If a Python strategy
+    /// raises, or returns a move it can't make, play stops before that move
+    /// is made and the error is raised from here.
+    #[text_signature = "($self)"]
+    fn run(&mut self, py: Python) -> PyResult<PyHandResult> {
+        let g = &mut self.g;
+        let errors = &self.errors;
+        let failed = || errors.lock().unwrap().is_some();
+
+        py.allow_threads(move || {
+            while let Some(decision) = g.decision() {
+                let seat = g.curr_player_id;
+
+                match decision {
+                    Decision::Chain => {
+                        let chain = g.players[seat].choose_next(&g.table, &g.log);
+                        if failed() { break; }
+                        g.play(chain);
+                    },
+                    Decision::Suit => {
+                        let suit = g.players[seat].choose_suit(&g.log);
+                        if failed() { break; }
+                        g.nominate(suit);
+                    }
+                }
+            }
+        });
+
+        if let Some(e) = self.errors.lock().unwrap().take() { return Err(e); }
+
+        let result = self.g.result().cloned().unwrap();
+
+        Ok(PyHandResult {
+            winner: result.winner,
+            points: result.points,
+            scores: self.g.players.iter().map(|p| p.score).collect(),
+            remaining: result.remaining,
+            log: self.g.log.clone()
+        })
+    }
+
+    /// Each player's hand, by seat.
+    #[getter]
+    fn hands(&self) -> Vec<Vec<PyCard>> {
+        self.g.players.iter().map(|p| p.hand.iter().map(|card| PyCard { card }).collect()).collect()
+    }
+
+    /// Running totals, by seat.
+    #[getter]
+    fn scores(&self) -> Vec<i32> {
+        self.g.players.iter().map(|p| p.score).collect()
+    }
+
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        self.g.players.iter().map(|p| p.name.clone()).collect()
+    }
+
+    #[getter]
+    fn deck_size(&self) -> usize {
+        self.g.deck.len()
+    }
+
+    /// Oldest first; the last card is the one on top.
+    #[getter]
+    fn discard_pile(&self) -> Vec<PyCard> {
+        py_cards(&self.g.discard_pile)
+    }
+
+    #[getter]
+    fn top_card(&self) -> PyCard {
+        PyCard { card: self.g.table.top_card }
+    }
+
+    #[getter]
+    fn current_player(&self) -> usize {
+        self.g.curr_player_id
+    }
+
+    #[getter]
+    fn log(&self) -> Vec<PyTurn> {
+        py_log(&self.g.log)
+    }
+
+    #[getter]
+    fn rules(&self) -> PyRules {
+        PyRules { rules: (*self.g.table.rules).clone() }
+    }
+}
+
+/// Seats a Python object in a Rust game. The object must provide
+/// `choose_next(hand, log)`, returning a list of `Card`s (an empty list to
+/// pick up), and `choose_suit(hand, log)`, returning a `Suit`. The hand is a
+/// list of `Card`s and the log a list of `Turn`s. Card and suit codes such as
+/// `"XD"` and `"H"` are accepted in place of `Card`s and `Suit`s.
+///
+/// An exception from either method, or a chain that can't be parsed or
+/// played, is kept in `errors` for the game to raise, and the game stops
+/// before making the move; the strategy answers with a pick up or the suit
+/// it holds most of, which are never played.
+pub struct PythonStrategy
+{
+    object: PyObject,
+    name: String,
+    errors: Errors
+}
+
+/// The first error from a Python strategy in a game, shared between the
+/// strategies and the game seating them.
+type Errors = Arc<Mutex<Option<PyErr>>>;
+
+impl PythonStrategy
+{
+    pub fn new(py: Python, object: PyObject, errors: Errors) -> PythonStrategy
+    {
+        let name = object
+                    .as_ref(py)
+                    .get_type()
+                    .name()
+                    .map(|n| format!("Python {}", n))
+                    .unwrap_or_else(|_| "Python".to_string());
+
+        PythonStrategy { object, name, errors }
+    }
+
+    /// The method's answer converted by `extract`, or `None` if it or an
+    /// earlier call failed.
+    fn call<T, F>(&self, method: &str, hand: &CardSet, log: &[Turn], extract: F) -> Option<T>
+    where
+        F : FnOnce(&PyAny) -> Option<T>
+    {
+        if self.errors.lock().unwrap().is_some() { return None; }
+
+        Python::with_gil(|py| {
+            let hand : Vec<PyCard> = hand.iter().map(|card| PyCard { card }).collect();
+
+            let answer = match self.object.call_method1(py, method, (hand, py_log(log))) {
+                Ok(answer) => answer,
+                Err(e) => { self.fail(e); return None; }
+            };
+
+            let answer = answer.as_ref(py);
+            let extracted = extract(answer);
+
+            if extracted.is_none() {
+                let shown = answer.repr().map_or_else(|_| "?".to_string(), |r| r.to_string());
+                self.fail(PyValueError::new_err(format!("{}.{} returned {}, which isn't understood", self.name, method, shown)));
+            }
+
+            extracted
+        })
+    }
+
+    fn fail(&self, e: PyErr)
+    {
+        self.errors.lock().unwrap().get_or_insert(e);
+    }
+}
+
+/// Whether `chain` is in `hand` and can be played now. Empty picks up,
+/// which is only allowed when nothing goes, as in `env`.
+fn is_legal(table: &TableState, hand: &CardSet, chain: &[Card]) -> bool
+{
+    let mut left = *hand;
+
+    if chain.is_empty() { return !can_go(table, hand); }
+
+    chain.iter().all(|c| left.remove(c)) && is_valid(table, chain)
+}
+
+impl Strategy for PythonStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>
+    {
+        let chain = self.call("choose_next", hand, log, |answer| match answer.extract::<Vec<PyCard>>() {
+            Ok(cards) => Some(cards.into_iter().map(|c| c.card).collect()),
+            Err(_) => answer.extract::<Vec<String>>().ok().and_then(|codes| parse_chain(&codes.join(",")))
+        });
+
+        match chain {
+            Some(chain) if is_legal(table, hand, &chain) => chain,
+            Some(chain) => {
+                self.fail(PyValueError::new_err(format!("{} returned {:?}, which can't be played from {:?} on {:?}",
+                    self.name, chain, hand, table.top_card)));
+                Vec::new()
+            },
+            None => Vec::new()
+        }
+    }
+
+    fn choose_suit(&mut self, hand: &CardSet, log: &[Turn]) -> Suit
+    {
+        let suit = self.call("choose_suit", hand, log, |answer| match answer.extract::<PySuit>() {
+            Ok(suit) => Some(suit.suit),
+            Err(_) => answer.extract::<&str>().ok().and_then(suit_named)
+        });
+
+        suit.unwrap_or_else(|| shared::choose_suit(hand))
+    }
+
+    fn name(&self) -> &str { &self.name }
+}
+
+fn compare<T: Ord>(a: &T, b: &T, op: CompareOp) -> bool
+{
+    match op {
+        CompareOp::Lt => a < b,
+        CompareOp::Le => a <= b,
+        CompareOp::Eq => a == b,
+        CompareOp::Ne => a != b,
+        CompareOp::Gt => a > b,
+        CompareOp::Ge => a >= b
+    }
+}
+
+fn py_option(value: Option<usize>) -> String
+{
+    value.map_or_else(|| "None".to_string(), |v| v.to_string())
+}
+
+fn hash<T: Hash>(value: &T) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A suit, made from its letter (`Suit("S")`) and compared by `index`.
+#[pyclass(name = "Suit")]
+#[derive(Clone)]
+pub struct PySuit
+{
+    pub suit: Suit
+}
+
+#[pymethods]
+impl PySuit
+{
+    #[new]
+    fn new(code: &str) -> PyResult<PySuit>
+    {
+        let mut chars = code.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Suit::try_from(c).map(|suit| PySuit { suit }).map_err(PyValueError::new_err),
+            _ => Err(PyValueError::new_err(format!("'{}' is not a suit", code)))
+        }
+    }
+
+    /// Spades, clubs, hearts and diamonds.
+    #[staticmethod]
+    fn all() -> Vec<PySuit>
+    {
+        Suit::all().iter().map(|s| PySuit { suit: *s }).collect()
+    }
+
+    #[getter]
+    fn code(&self) -> String { self.suit.code().to_string() }
+
+    #[getter]
+    fn symbol(&self) -> String { format!("{:?}", self.suit) }
+
+    #[getter]
+    fn index(&self) -> usize { self.suit.index() }
+
+    #[getter]
+    fn is_red(&self) -> bool { self.suit.is_red() }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PySuit
+{
+    fn __str__(&self) -> String { self.code() }
+    fn __repr__(&self) -> String { format!("Suit('{}')", self.code()) }
+    fn __hash__(&self) -> u64 { hash(&self.suit) }
+    fn __richcmp__(&self, other: PyRef<PySuit>, op: CompareOp) -> bool { compare(&self.suit.index(), &other.suit.index(), op) }
+}
+
+/// A rank, made from its character (`Rank("X")`) and ordered two to ace.
+#[pyclass(name = "Rank")]
+#[derive(Clone)]
+pub struct PyRank
+{
+    pub rank: Rank
+}
+
+#[pymethods]
+impl PyRank
+{
+    #[new]
+    fn new(code: &str) -> PyResult<PyRank>
+    {
+        let mut chars = code.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Rank::try_from(c).map(|rank| PyRank { rank }).map_err(PyValueError::new_err),
+            _ => Err(PyValueError::new_err(format!("'{}' is not a rank", code)))
+        }
+    }
+
+    /// Two to ace.
+    #[staticmethod]
+    fn all() -> Vec<PyRank>
+    {
+        Rank::all().iter().map(|r| PyRank { rank: *r }).collect()
+    }
+
+    #[getter]
+    fn code(&self) -> String { format!("{:?}", self.rank) }
+
+    /// 2 to 10, then 11 to 14 for jack to ace.
+    #[getter]
+    fn value(&self) -> usize { self.rank.index() + 2 }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyRank
+{
+    fn __str__(&self) -> String { self.code() }
+    fn __repr__(&self) -> String { format!("Rank('{}')", self.code()) }
+    fn __hash__(&self) -> u64 { hash(&self.rank) }
+    fn __richcmp__(&self, other: PyRef<PyRank>, op: CompareOp) -> bool { compare(&self.rank, &other.rank, op) }
+}
+
+/// A card, made from its code (`Card("XD")`, `Card("JR")`) and ordered by
+/// suit then rank, with the jokers last.
+#[pyclass(name = "Card")]
+#[derive(Clone)]
+pub struct PyCard
+{
+    pub card: Card
+}
+
+#[pymethods]
+impl PyCard
+{
+    #[new]
+    fn new(code: &str) -> PyResult<PyCard>
+    {
+        Card::try_from(code).map(|card| PyCard { card }).map_err(PyValueError::new_err)
+    }
+
+    #[getter]
+    fn code(&self) -> String { self.card.code() }
+
+    /// `None` for a joker.
+    #[getter]
+    fn rank(&self) -> Option<PyRank>
+    {
+        if self.card.is_joker() { None } else { Some(PyRank { rank: self.card.rank() }) }
+    }
+
+    /// `None` for a joker.
+    #[getter]
+    fn suit(&self) -> Option<PySuit>
+    {
+        if self.card.is_joker() { None } else { Some(PySuit { suit: self.card.suit() }) }
+    }
+
+    #[getter]
+    fn is_joker(&self) -> bool { self.card.is_joker() }
+
+    /// Position in a 54 long one-hot encoding.
+    #[getter]
+    fn index(&self) -> usize { self.card.index() }
+
+    /// Penalty points the card is worth left in a hand under the standard
+    /// rules; `Rules.card_score` scores it under others.
+    #[getter]
+    fn score(&self) -> i32 { Rules::default().card_score(self.card) }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyCard
+{
+    fn __str__(&self) -> String { self.code() }
+    fn __repr__(&self) -> String { format!("Card('{}')", self.code()) }
+    fn __hash__(&self) -> u64 { hash(&self.card) }
+    fn __richcmp__(&self, other: PyRef<PyCard>, op: CompareOp) -> bool { compare(&self.card, &other.card, op) }
+}
+
+pub fn py_cards<'a, I: IntoIterator<Item = &'a Card>>(cards: I) -> Vec<PyCard>
+{
+    cards.into_iter().map(|card| PyCard { card: *card }).collect()
+}
+
+/// What a player did: `kind` is `"played"`, `"nominated"`, `"picked_up"`,
+/// `"first"` or `"skipped"`, with `cards`, `suit` or `count` to match.
+#[pyclass(name = "Action")]
+#[derive(Clone)]
+pub struct PyAction
+{
+    pub action: Action
+}
+
+#[pymethods]
+impl PyAction
+{
+    #[getter]
+    fn kind(&self) -> &str
+    {
+        match self.action {
+            Action::Played(_) => "played",
+            Action::Nominated(_) => "nominated",
+            Action::PickedUp(_) => "picked_up",
+            Action::First(_) => "first",
+            Action::Skipped => "skipped"
+        }
+    }
+
+    /// The cards played, or the first card turned up; empty otherwise.
+    #[getter]
+    fn cards(&self) -> Vec<PyCard>
+    {
+        match &self.action {
+            Action::Played(chain) => py_cards(chain),
+            Action::First(card) => py_cards(&[*card]),
+            _ => Vec::new()
+        }
+    }
+
+    #[getter]
+    fn suit(&self) -> Option<PySuit>
+    {
+        match self.action {
+            Action::Nominated(suit) => Some(PySuit { suit }),
+            _ => None
+        }
+    }
+
+    #[getter]
+    fn count(&self) -> Option<usize>
+    {
+        match self.action {
+            Action::PickedUp(n) => Some(n),
+            _ => None
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyAction
+{
+    fn __repr__(&self) -> String { format!("Action({:?})", self.action) }
+}
+
+/// One entry in the game log; `player` is `None` for the first card.
+#[pyclass(name = "Turn")]
+#[derive(Clone)]
+pub struct PyTurn
+{
+    pub turn: Turn
+}
+
+#[pymethods]
+impl PyTurn
+{
+    #[getter]
+    fn player(&self) -> Option<usize> { self.turn.player }
+
+    #[getter]
+    fn action(&self) -> PyAction { PyAction { action: self.turn.action.clone() } }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyTurn
+{
+    fn __repr__(&self) -> String { format!("Turn(player={}, {:?})", py_option(self.turn.player), self.turn.action) }
+}
+
+pub fn py_log(log: &[Turn]) -> Vec<PyTurn>
+{
+    log.iter().map(|turn| PyTurn { turn: turn.clone() }).collect()
+}
+
+/// The rules to play with; every argument defaults to the standard game.
+/// `effects` is a list such as `"2=penalty:2,J-black=penalty:5,8=skip"`
+/// and `scoring` one of `"classic"`, `"winner-takes-all"` or `"wins"`.
+#[pyclass(name = "Rules")]
+#[derive(Clone)]
+pub struct PyRules
+{
+    pub rules: Rules
+}
+
+#[pymethods]
+impl PyRules
+{
+    #[new]
+    #[args(jokers_per_deck = "0", joker_penalty = "0", effects = "None", stacking_skips = "false",
+           scoring = "\"classic\"", penalty_finish_bonus = "0")]
+    fn new(jokers_per_deck: usize, joker_penalty: usize, effects: Option<&str>, stacking_skips: bool,
+           scoring: &str, penalty_finish_bonus: i32) -> PyResult<PyRules>
+    {
+        let mut rules = Rules::default();
+
+        rules.jokers_per_deck = jokers_per_deck;
+        rules.joker_penalty = joker_penalty;
+        rules.stacking_skips = stacking_skips;
+        rules.scoring = Scoring::parse(scoring).map_err(PyValueError::new_err)?;
+        rules.penalty_finish_bonus = penalty_finish_bonus;
+
+        if let Some(effects) = effects { rules.set_effects(effects).map_err(PyValueError::new_err)?; }
+
+        rules.validate().map_err(PyValueError::new_err)?;
+
+        Ok(PyRules { rules })
+    }
+
+    #[getter]
+    fn jokers_per_deck(&self) -> usize { self.rules.jokers_per_deck }
+
+    #[getter]
+    fn joker_penalty(&self) -> usize { self.rules.joker_penalty }
+
+    #[getter]
+    fn effects(&self) -> String { self.rules.effects_spec() }
+
+    #[getter]
+    fn stacking_skips(&self) -> bool { self.rules.stacking_skips }
+
+    #[getter]
+    fn penalty_finish_bonus(&self) -> i32 { self.rules.penalty_finish_bonus }
+
+    /// Whether lower scores are better under these rules.
+    #[getter]
+    fn lower_is_better(&self) -> bool { self.rules.scoring.lower_is_better() }
+
+    /// Points `card` is worth left in a hand under these rules.
+    #[text_signature = "($self, card)"]
+    fn card_score(&self, card: PyRef<PyCard>) -> i32 { self.rules.card_score(card.card) }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyRules
+{
+    fn __repr__(&self) -> String { format!("{:?}", self.rules) }
+}
+
+/// How a hand ended, by seat: `winner` is `None` if nobody could go.
+#[pyclass(name = "HandResult")]
+pub struct PyHandResult
+{
+    #[pyo3(get)]
+    pub winner: Option<usize>,
+    #[pyo3(get)]
+    pub points: Vec<i32>,
+    #[pyo3(get)]
+    pub scores: Vec<i32>,
+    pub remaining: Vec<CardSet>,
+    pub log: Vec<Turn>
+}
+
+#[pymethods]
+impl PyHandResult
+{
+    /// The cards each player was left holding.
+    #[getter]
+    fn remaining(&self) -> Vec<Vec<PyCard>>
+    {
+        self.remaining.iter().map(|hand| hand.iter().map(|card| PyCard { card }).collect()).collect()
+    }
+
+    #[getter]
+    fn log(&self) -> Vec<PyTurn> { py_log(&self.log) }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyHandResult
+{
+    fn __repr__(&self) -> String { format!("HandResult(winner={}, points={:?})", py_option(self.winner), self.points) }
+}
+
+/// `values` as a read-only `memoryview` of `shape` in the `struct` module
+/// `format`, which `numpy.asarray` wraps without copying.
+fn array<T, const N: usize>(py: Python, values: &[T], to_bytes: fn(&T) -> [u8; N], format: &str, shape: Vec<usize>) -> PyResult<PyObject>
+{
+    let bytes : Vec<u8> = values.iter().flat_map(to_bytes).collect();
+    let view = py.import("builtins")?.getattr("memoryview")?.call1((PyBytes::new(py, &bytes),))?;
+
+    Ok(view.call_method1("cast", (format, shape))?.into())
+}
+
+fn f32_array(py: Python, values: &[f32], shape: Vec<usize>) -> PyResult<PyObject>
+{
+    array(py, values, |x| x.to_ne_bytes(), "f", shape)
+}
+
+fn bool_array(py: Python, values: &[bool], shape: Vec<usize>) -> PyResult<PyObject>
+{
+    array(py, values, |b| [*b as u8], "?", shape)
+}
+
+/// `n` games seen from `seat`, with `opponents` (registry specs) in the
+/// other seats in order, stepped together with the GIL released.
+/// Observations are an `(n, observation_size)` float32 array and masks an
+/// `(n, action_size)` bool array, as memoryviews for `numpy.asarray`;
+/// finished games are dealt again straight away, so the observation after
+/// a `done` is the first of the next hand.
+#[pyclass(name = "VecEnv")]
+pub struct PyVecEnv
+{
+    env: VecEnv
+}
+
+type Observations = (PyObject, PyObject);
+type Transitions = (PyObject, PyObject, PyObject, PyObject);
+
+#[pymethods]
+impl PyVecEnv
+{
+    #[new]
+    #[args(rules = "None", seat = "0")]
+    fn new(n: usize, opponents: Vec<String>, rules: Option<PyRules>, seat: usize) -> PyResult<PyVecEnv>
+    {
+        let registry = Registry::standard();
+
+        let opponents = opponents
+                        .iter()
+                        .map(|spec| registry.parse(spec))
+                        .collect::<Result<Vec<StrategyType>, String>>()
+                        .map_err(PyValueError::new_err)?;
+
+        let rules = rules.map(|r| r.rules).unwrap_or_default();
+
+        VecEnv::new(n, seat, &opponents, &rules)
+            .map(|env| PyVecEnv { env })
+            .map_err(PyValueError::new_err)
+    }
+
+    #[classattr]
+    fn observation_size() -> usize { OBSERVATION_SIZE }
+
+    #[classattr]
+    fn action_size() -> usize { ACTION_SIZE }
+
+    /// The action that plays the chain built so far, or picks up.
+    #[classattr]
+    fn end_chain() -> usize { END_CHAIN }
+
+    #[getter]
+    fn num_envs(&self) -> usize { self.env.envs.len() }
+
+    /// Deals every game again, returning `(observations, masks)`.
+    fn reset(&mut self, py: Python) -> PyResult<Observations>
+    {
+        let env = &mut self.env;
+        let n = env.envs.len();
+
+        let (observations, masks) = py.allow_threads(move || {
+            env.reset();
+            (env.observations(), env.masks())
+        });
+
+        Ok((f32_array(py, &observations, vec![n, OBSERVATION_SIZE])?, bool_array(py, &masks, vec![n, ACTION_SIZE])?))
+    }
+
+    /// Returns `(observations, rewards, dones, masks)`, with rewards and
+    /// dones one per game.
+    fn step(&mut self, py: Python, actions: Vec<usize>) -> PyResult<Transitions>
+    {
+        let env = &mut self.env;
+        let n = env.envs.len();
+
+        let (observations, rewards, dones, masks) = py.allow_threads(move || -> Result<_, String> {
+            let (rewards, dones) = env.step(&actions)?;
+            Ok((env.observations(), rewards, dones, env.masks()))
+        })
+        .map_err(PyValueError::new_err)?;
+
+        Ok((f32_array(py, &observations, vec![n, OBSERVATION_SIZE])?,
+            f32_array(py, &rewards, vec![n])?,
+            bool_array(py, &dones, vec![n])?,
+            bool_array(py, &masks, vec![n, ACTION_SIZE])?))
+    }
+}
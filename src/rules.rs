@@ -0,0 +1,753 @@
+use crate::cards::*;
+use crate::game::*;
+
+use std::convert::TryFrom;
+use std::sync::{Arc, OnceLock};
+
+pub const STARTING_CARD_COUNT : usize = 7;
+
+/// Players a single deck is dealt to; larger tables shuffle in more decks.
+pub const PLAYERS_PER_DECK : usize = 6;
+
+pub const MAX_PLAYERS : usize = PLAYERS_PER_DECK * MAX_DECKS;
+
+pub fn decks_for(players: usize) -> usize
+{
+    players.div_ceil(PLAYERS_PER_DECK)
+}
+
+/// What playing a card does, beyond following and linking by suit and rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Effect
+{
+    /// The next player misses a go unless they answer with another skip card.
+    Skip,
+    /// The next player picks up this many cards unless they pass the penalty
+    /// on, adding to it.
+    Penalty(usize),
+    /// Play changes direction.
+    Reverse,
+    /// Must be covered: it links to any card of its suit without an effect,
+    /// and a player ending their chain on it picks up one.
+    Cover,
+    /// Follows anything and lets the player nominate the next suit, but only
+    /// links to other wild cards.
+    Wild
+}
+
+impl std::fmt::Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Skip => f.write_str("skip"),
+            Effect::Penalty(n) => write!(f, "penalty:{}", n),
+            Effect::Reverse => f.write_str("reverse"),
+            Effect::Cover => f.write_str("cover"),
+            Effect::Wild => f.write_str("wild")
+        }
+    }
+}
+
+impl Effect
+{
+    /// Parses the `Display` form, or `"none"` for no effect.
+    pub fn parse(s: &str) -> Result<Option<Effect>, String>
+    {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(None),
+            "skip" => Ok(Some(Effect::Skip)),
+            "reverse" => Ok(Some(Effect::Reverse)),
+            "cover" => Ok(Some(Effect::Cover)),
+            "wild" => Ok(Some(Effect::Wild)),
+            e => match e.strip_prefix("penalty:").map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => Ok(Some(Effect::Penalty(n))),
+                _ => Err(format!("'{}' is not an effect", s))
+            }
+        }
+    }
+}
+
+/// How a hand is scored once someone goes out, or nobody can go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scoring
+{
+    /// Everyone scores the `Rules::card_score` of the cards left in their
+    /// hand; lower is better.
+    Classic,
+    /// The winner scores the `Rules::card_score` of every card left in
+    /// their opponents' hands and everyone else nothing; higher is better.
+    WinnerTakesAll,
+    /// The winner scores one, so totals count hands won; higher is better.
+    Wins
+}
+
+impl Scoring
+{
+    pub fn parse(s: &str) -> Result<Scoring, String>
+    {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "classic" => Ok(Scoring::Classic),
+            "winner-takes-all" => Ok(Scoring::WinnerTakesAll),
+            "wins" => Ok(Scoring::Wins),
+            _ => Err(format!("'{}' is not classic, winner-takes-all or wins", s))
+        }
+    }
+
+    pub fn lower_is_better(&self) -> bool
+    {
+        *self == Scoring::Classic
+    }
+}
+
+/// The effects of the standard game, in the form `Rules::set_effects` takes.
+pub const STANDARD_EFFECTS : &str = "2=penalty:2,J-black=penalty:5,8=skip,K=cover,A=wild";
+
+/// The rules a game is played with; the default is the standard game.
+/// Card effects are held per card, along with the `can_link` relation they
+/// imply, so the rules never need to be looked up by rank.
+#[derive(Clone, PartialEq)]
+pub struct Rules
+{
+    /// Jokers shuffled into each deck, from none up to both. Jokers are wild:
+    /// they follow anything and link to anything.
+    pub jokers_per_deck: usize,
+    /// Cards the next player picks up after a joker, or 0 for none.
+    pub joker_penalty: usize,
+    /// Whether each skip card in a chain makes one more player miss a go,
+    /// rather than only the next player missing one however many are played.
+    pub stacking_skips: bool,
+    pub scoring: Scoring,
+    /// Points in the winner's favour for going out on a penalty card.
+    pub penalty_finish_bonus: i32,
+    effects: [Option<Effect>; 52],
+    links: [CardSet; CARD_TYPES]
+}
+
+impl Default for Rules
+{
+    fn default() -> Rules
+    {
+        static STANDARD : OnceLock<Rules> = OnceLock::new();
+
+        STANDARD.get_or_init(|| {
+            let mut rules = Rules {
+                jokers_per_deck: 0,
+                joker_penalty: 0,
+                stacking_skips: false,
+                scoring: Scoring::Classic,
+                penalty_finish_bonus: 0,
+                effects: [None; 52],
+                links: [CardSet::new(); CARD_TYPES]
+            };
+            rules.set_effects(STANDARD_EFFECTS).unwrap();
+            rules
+        }).clone()
+    }
+}
+
+impl std::fmt::Debug for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rules")
+            .field("jokers_per_deck", &self.jokers_per_deck)
+            .field("joker_penalty", &self.joker_penalty)
+            .field("stacking_skips", &self.stacking_skips)
+            .field("scoring", &self.scoring)
+            .field("penalty_finish_bonus", &self.penalty_finish_bonus)
+            .field("effects", &self.effects_spec())
+            .finish()
+    }
+}
+
+impl Rules
+{
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if self.jokers_per_deck > 2 {
+            return Err(format!("{} jokers per deck is more than 2 max", self.jokers_per_deck));
+        }
+
+        Ok(())
+    }
+
+    /// Each player's points for a hand, given what is left in their hands
+    /// and who went out, if anyone did.
+    pub fn score_hand(&self, hands: &[CardSet], winner: Option<usize>, finished_on_penalty: bool) -> Vec<i32>
+    {
+        let hand_score = |hand: &CardSet| hand.iter().map(|c| self.card_score(c)).sum::<i32>();
+
+        let mut points : Vec<i32> = match self.scoring {
+            Scoring::Classic => hands.iter().map(hand_score).collect(),
+            Scoring::WinnerTakesAll => {
+                let pot = hands.iter().map(hand_score).sum();
+                (0..hands.len()).map(|i| if Some(i) == winner { pot } else { 0 }).collect()
+            },
+            Scoring::Wins => (0..hands.len()).map(|i| (Some(i) == winner) as i32).collect()
+        };
+
+        if let Some(w) = winner.filter(|_| finished_on_penalty) {
+            let sign = if self.scoring.lower_is_better() { -1 } else { 1 };
+            points[w] += sign * self.penalty_finish_bonus;
+        }
+
+        points
+    }
+
+    /// Every card from `decks` decks, including any jokers.
+    pub fn deck(&self, decks: usize) -> CardSet
+    {
+        let mut deck = CardSet::decks(decks);
+
+        for _ in 0..decks {
+            for colour in [Colour::Red, Colour::Black].iter().take(self.jokers_per_deck) {
+                deck.insert(Card::joker(*colour));
+            }
+        }
+
+        deck
+    }
+
+    /// The effect of playing `card`. Jokers have a penalty if the rules give
+    /// them one.
+    pub fn effect(&self, card: Card) -> Option<Effect>
+    {
+        if card.is_joker() {
+            return if self.joker_penalty > 0 { Some(Effect::Penalty(self.joker_penalty)) } else { None };
+        }
+
+        self.effects[card.index()]
+    }
+
+    pub fn penalty_value(&self, card: Card) -> usize
+    {
+        match self.effect(card) {
+            Some(Effect::Penalty(n)) => n,
+            _ => 0
+        }
+    }
+
+    /// Points `card` is worth left in a hand: 50 for a joker, 25 for a wild
+    /// card, 20 for a penalty, skip or reverse card, and otherwise its face
+    /// value.
+    pub fn card_score(&self, card: Card) -> i32
+    {
+        if card.is_joker() { return 50; }
+
+        match self.effect(card) {
+            Some(Effect::Wild) => 25,
+            Some(Effect::Penalty(_)) | Some(Effect::Skip) | Some(Effect::Reverse) => 20,
+            Some(Effect::Cover) | None => card.rank().face_value()
+        }
+    }
+
+    /// The cards that may follow `card` in a chain.
+    pub fn links(&self, card: Card) -> CardSet
+    {
+        self.links[card.index()]
+    }
+
+    /// Replaces every effect from a list such as `STANDARD_EFFECTS`: comma
+    /// separated `cards=effect` pairs, where the cards are a rank alone, a
+    /// rank and suit (`QS`) or a rank and colour (`J-black`), and the effect
+    /// is as `Effect` displays, or `none`. Later pairs override earlier ones.
+    pub fn set_effects(&mut self, spec: &str) -> Result<(), String>
+    {
+        let mut effects = [None; 52];
+
+        for pair in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+
+            let (cards, effect) = pair.split_once('=')
+                                    .ok_or_else(|| format!("Expected cards=effect, not '{}'", pair))?;
+
+            let effect = Effect::parse(effect)?;
+
+            for card in matching_cards(cards)? { effects[card.index()] = effect; }
+        }
+
+        self.effects = effects;
+        self.update_links();
+
+        Ok(())
+    }
+
+    fn update_links(&mut self)
+    {
+        let mut links = [CardSet::new(); CARD_TYPES];
+
+        for (i, set) in links.iter_mut().enumerate() {
+            let prev = Card::from_index(i);
+            *set = (0..CARD_TYPES).map(Card::from_index).filter(|next| can_link(self, prev, *next)).collect();
+        }
+
+        self.links = links;
+    }
+
+    /// The effects in the form `set_effects` takes, grouping the suits of
+    /// each rank where they agree.
+    pub fn effects_spec(&self) -> String
+    {
+        let mut pairs = Vec::new();
+
+        for rank in Rank::all().iter() {
+
+            let effect = |suit: Suit| self.effects[Card::new(*rank, suit).index()];
+            let [spades, clubs, hearts, diamonds] = Suit::all();
+
+            let groups : Vec<(String, Option<Effect>)> =
+                if [clubs, hearts, diamonds].iter().all(|s| effect(*s) == effect(spades)) {
+                    vec![(String::new(), effect(spades))]
+                } else if effect(spades) == effect(clubs) && effect(hearts) == effect(diamonds) {
+                    vec![("-black".to_string(), effect(spades)), ("-red".to_string(), effect(hearts))]
+                } else {
+                    Suit::all().iter().map(|s| (s.code().to_string(), effect(*s))).collect()
+                };
+
+            for (suffix, effect) in groups {
+                if let Some(effect) = effect { pairs.push(format!("{:?}{}={}", rank, suffix, effect)); }
+            }
+        }
+
+        pairs.join(",")
+    }
+}
+
+/// The cards a pattern in an effects list stands for: `8`, `QS` or `J-black`.
+fn matching_cards(pattern: &str) -> Result<Vec<Card>, String>
+{
+    let pattern = pattern.trim();
+    let mut chars = pattern.chars();
+
+    let rank = Rank::try_from(chars.next().ok_or("Missing rank")?)?;
+    let rest = chars.as_str();
+
+    let suits : Vec<Suit> = match rest.to_ascii_lowercase().as_str() {
+        "" => Suit::all().to_vec(),
+        "-black" => Suit::all().iter().copied().filter(|s| s.is_black()).collect(),
+        "-red" => Suit::all().iter().copied().filter(|s| s.is_red()).collect(),
+        _ => match (rest.chars().next(), rest.chars().count()) {
+            (Some(c), 1) => vec![Suit::try_from(c)?],
+            _ => return Err(format!("'{}' is not a rank, rank and suit or rank and colour", pattern))
+        }
+    };
+
+    Ok(suits.into_iter().map(|s| Card::new(rank, s)).collect())
+}
+
+/// What the next player faces, derived from the log one turn at a time so
+/// the rules never need to rescan it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableState
+{
+    /// The rules the game is played with.
+    pub rules: Arc<Rules>,
+    /// The last card played.
+    pub top_card: Card,
+    /// The suit nominated with a wild card, until the next card is played.
+    pub nominated: Option<Suit>,
+    /// Whether the top card's effect (penalty, skip) still applies; cleared
+    /// once someone picks up or misses a go.
+    pub active: bool,
+    /// Cards the next player must pick up if they cannot pass the penalty on.
+    pub penalty: usize,
+    /// Players still to miss a go. While `active` the next player can pass
+    /// the skip on with a skip card; once one player has missed a go the
+    /// rest are skipped in turn.
+    pub skips: usize,
+    /// Whether play has been reversed an odd number of times.
+    pub reversed: bool
+}
+
+impl TableState
+{
+    pub fn new(rules: Arc<Rules>, first: Card) -> TableState
+    {
+        TableState {
+            penalty: rules.penalty_value(first),
+            skips: (rules.effect(first) == Some(Effect::Skip)) as usize,
+            reversed: rules.effect(first) == Some(Effect::Reverse),
+            rules,
+            top_card: first,
+            nominated: None,
+            active: true
+        }
+    }
+
+    pub fn apply(&mut self, turn: &Turn)
+    {
+        match &turn.action {
+            Action::Played(chain) => {
+                let trailing = chain
+                                .iter()
+                                .rev()
+                                .map(|c| self.rules.penalty_value(*c))
+                                .take_while(|p| *p > 0)
+                                .collect::<Vec<usize>>();
+
+                if trailing.len() < chain.len() || !self.active { self.penalty = 0; }
+
+                let is_skip = |c: &&Card| self.rules.effect(**c) == Some(Effect::Skip);
+
+                let trailing_skips = chain.iter().rev().take_while(is_skip).count();
+                let reverses = chain.iter().filter(|c| self.rules.effect(**c) == Some(Effect::Reverse)).count();
+
+                self.skips = match (self.rules.stacking_skips, trailing_skips) {
+                    (_, 0) => 0,
+                    (false, _) => 1,
+                    (true, n) if n == chain.len() && self.active => self.skips + n,
+                    (true, n) => n
+                };
+
+                self.penalty += trailing.iter().sum::<usize>();
+                self.top_card = *chain.last().unwrap();
+                self.nominated = None;
+                self.active = true;
+                self.reversed ^= reverses % 2 == 1;
+            },
+            Action::First(card) => { *self = TableState::new(self.rules.clone(), *card); },
+            Action::Nominated(suit) => {
+                self.nominated = Some(*suit);
+                self.penalty = 0;
+            },
+            Action::PickedUp(_) => {
+                self.active = false;
+                self.penalty = 0;
+                self.skips = 0;
+            },
+            Action::Skipped => {
+                self.active = false;
+                self.penalty = 0;
+                self.skips = self.skips.saturating_sub(1);
+            }
+        }
+    }
+}
+
+pub fn can_follow(table: &TableState, next: Card) -> bool
+{
+    let rules = &table.rules;
+
+    if next.is_joker() { return true; }
+
+    let wild = rules.effect(next) == Some(Effect::Wild);
+
+    if let Some(s) = table.nominated {
+        return next.suit() == s || wild;
+    }
+
+    let prev = table.top_card;
+
+    if prev.is_joker() {
+        return table.penalty == 0 || rules.penalty_value(next) > 0;
+    }
+
+    let same_rank = prev.rank() == next.rank();
+
+    match (table.active, rules.effect(prev)) {
+        // A penalty is passed on with the same rank, or a penalty no bigger
+        // in the same suit.
+        (true, Some(Effect::Penalty(n))) => {
+            same_rank || (prev.suit() == next.suit() && matches!(rules.effect(next), Some(Effect::Penalty(m)) if m <= n))
+        },
+        (true, Some(Effect::Skip)) => {
+            same_rank || rules.effect(next) == Some(Effect::Skip)
+        },
+        _ => wild || prev.suit() == next.suit() || same_rank
+    }
+}
+
+/// Whether `next` may be played straight after `prev` in a chain. With more
+/// than one deck `prev` and `next` can be identical copies, which always
+/// link: they share a rank, and wild cards only ever link to wild cards. A
+/// joker links either way to anything, though in a chain it must stand in
+/// for a single card that links to both its neighbours (see `chain_ends`).
+pub fn can_link(rules: &Rules, prev: Card, next: Card) -> bool
+{
+    if prev.is_joker() || next.is_joker() { return true; }
+
+    let wild = |c: Card| rules.effect(c) == Some(Effect::Wild);
+
+    if wild(prev) ^ wild(next) {
+        return false;
+    }
+
+    prev.rank() == next.rank()
+    || (prev.suit() == next.suit() && Rank::adjacent(prev.rank(), next.rank()))
+    || (rules.effect(prev) == Some(Effect::Cover)
+        && prev.suit() == next.suit()
+        && rules.effect(next).is_none())
+}
+
+pub fn can_go(table: &TableState, hand: &CardSet) -> bool
+{
+    hand.iter().any(|card| can_follow(table, card))
+}
+
+/// The cards a chain could be ending on once `next` is added to a chain
+/// that could be ending on any of `ends`, or nothing if `next` cannot be
+/// added. A real card ends the chain on itself; a joker on any card that
+/// links from one of `ends`, which is every card if it starts the chain.
+pub fn chain_ends(rules: &Rules, ends: Option<&CardSet>, next: Card) -> CardSet
+{
+    let links_from_end = |card: &Card| match ends {
+        Some(ends) => ends.iter().any(|end| rules.links(end).contains(card)),
+        None => true
+    };
+
+    if next.is_joker() {
+        CardSet::full().iter().filter(links_from_end).collect()
+    } else if links_from_end(&next) {
+        std::iter::once(next).collect()
+    } else {
+        CardSet::new()
+    }
+}
+
+pub fn is_valid(table: &TableState, chain: &[Card]) -> bool
+{
+    if !can_follow(table, chain[0]) { return false; }
+
+    let mut ends = chain_ends(&table.rules, None, chain[0]);
+
+    for next in &chain[1..]
+    {
+        ends = chain_ends(&table.rules, Some(&ends), *next);
+        if ends.is_empty() { return false; }
+    }
+
+    true
+}
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    fn with_effects(spec: &str) -> Rules
+    {
+        let mut rules = Rules::default();
+        rules.set_effects(spec).unwrap();
+        rules
+    }
+
+    #[test]
+    fn a_deck_for_every_six_players()
+    {
+        for (players, decks) in [(1, 1), (2, 1), (6, 1), (7, 2), (12, 2)] {
+            assert_eq!(decks_for(players), decks, "{} players", players);
+        }
+
+        assert!(decks_for(MAX_PLAYERS) <= MAX_DECKS);
+    }
+
+    #[test]
+    fn effects_parse_as_they_display()
+    {
+        for effect in [Effect::Skip, Effect::Penalty(3), Effect::Reverse, Effect::Cover, Effect::Wild] {
+            assert_eq!(Effect::parse(&effect.to_string()), Ok(Some(effect)));
+        }
+
+        assert_eq!(Effect::parse(" None "), Ok(None));
+        assert_eq!(Effect::parse("PENALTY:2"), Ok(Some(Effect::Penalty(2))));
+
+        for bad in ["penalty:0", "penalty:", "penalty:x", "jump", ""] {
+            assert!(Effect::parse(bad).is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn standard_effects_are_the_default()
+    {
+        assert_eq!(with_effects(STANDARD_EFFECTS), Rules::default());
+        assert_eq!(with_effects(&Rules::default().effects_spec()), Rules::default());
+
+        let rules = Rules::default();
+        assert_eq!(rules.effect(card("2D")), Some(Effect::Penalty(2)));
+        assert_eq!(rules.effect(card("JC")), Some(Effect::Penalty(5)));
+        assert_eq!(rules.effect(card("JH")), None);
+        assert_eq!(rules.effect(card("8S")), Some(Effect::Skip));
+        assert_eq!(rules.effect(card("KH")), Some(Effect::Cover));
+        assert_eq!(rules.effect(card("AD")), Some(Effect::Wild));
+        assert_eq!(rules.effect(card("JR")), None);
+    }
+
+    #[test]
+    fn patterns_pick_out_ranks_suits_and_colours()
+    {
+        let rules = with_effects("7=reverse, QS=penalty:4, 9-red=skip");
+
+        assert!(["7S", "7C", "7H", "7D"].iter().all(|c| rules.effect(card(c)) == Some(Effect::Reverse)));
+        assert_eq!(rules.effect(card("QS")), Some(Effect::Penalty(4)));
+        assert_eq!(rules.effect(card("QC")), None);
+        assert_eq!(rules.effect(card("9H")), Some(Effect::Skip));
+        assert_eq!(rules.effect(card("9S")), None);
+        assert_eq!(rules.effect(card("2S")), None, "unlisted cards lose their standard effect");
+    }
+
+    #[test]
+    fn later_pairs_override_earlier_ones()
+    {
+        let rules = with_effects("8=skip,8H=none,8-black=reverse");
+
+        assert_eq!(rules.effect(card("8S")), Some(Effect::Reverse));
+        assert_eq!(rules.effect(card("8H")), None);
+        assert_eq!(rules.effect(card("8D")), Some(Effect::Skip));
+    }
+
+    #[test]
+    fn effects_spec_round_trips()
+    {
+        for spec in ["7=reverse,QS=penalty:4,9-red=skip", "2-black=penalty:2,2-red=penalty:3", ""] {
+            let rules = with_effects(spec);
+            assert_eq!(with_effects(&rules.effects_spec()), rules, "{}", spec);
+        }
+
+        assert_eq!(with_effects("8S=skip,8C=skip").effects_spec(), "8-black=skip");
+    }
+
+    #[test]
+    fn bad_effect_lists_are_rejected()
+    {
+        for bad in ["8", "8=jump", "Z=skip", "QX=skip", "Q-blue=skip", "=skip", "QSH=skip"] {
+            assert!(Rules::default().set_effects(bad).is_err(), "{:?} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn effects_change_what_links()
+    {
+        let rules = with_effects("Q=wild,K=none");
+
+        assert!(rules.links(card("QS")).contains(&card("QH")));
+        assert!(!rules.links(card("QS")).contains(&card("KS")));
+        assert!(rules.links(card("AS")).contains(&card("KS")), "aces are no longer wild");
+        assert!(!rules.links(card("KH")).contains(&card("5H")), "a king without cover links by rank and run only");
+        assert!(Rules::default().links(card("KH")).contains(&card("5H")));
+    }
+
+    fn apply(table: &mut TableState, player: usize, action: Action)
+    {
+        table.apply(&Turn { player: Some(player), action });
+    }
+
+    fn played(codes: &[&str]) -> Action
+    {
+        Action::Played(codes.iter().map(|c| card(c)).collect())
+    }
+
+    #[test]
+    fn skips_stack_only_when_the_rules_say_so()
+    {
+        for (stacking, expected) in [(false, [1, 1, 1]), (true, [2, 3, 1])] {
+            let rules = Rules { stacking_skips: stacking, ..Rules::default() };
+
+            let mut table = TableState::new(Arc::new(rules), card("9S"));
+
+            apply(&mut table, 0, played(&["8S", "8H"]));
+            assert_eq!(table.skips, expected[0]);
+
+            apply(&mut table, 1, played(&["8C"]));
+            assert_eq!(table.skips, expected[1]);
+
+            apply(&mut table, 2, played(&["8D", "9D", "8D"]));
+            assert_eq!(table.skips, expected[2], "a chain that isn't all skips starts the count again");
+        }
+    }
+
+    #[test]
+    fn missing_a_go_counts_skips_down()
+    {
+        let rules = Rules { stacking_skips: true, ..Rules::default() };
+
+        let mut table = TableState::new(Arc::new(rules), card("9S"));
+        apply(&mut table, 0, played(&["8S", "8H", "8D"]));
+
+        apply(&mut table, 1, Action::Skipped);
+        assert_eq!((table.skips, table.active), (2, false));
+
+        apply(&mut table, 0, Action::Skipped);
+        apply(&mut table, 1, Action::Skipped);
+        assert_eq!(table.skips, 0);
+
+        apply(&mut table, 0, played(&["8C"]));
+        assert_eq!((table.skips, table.active), (1, true), "skips aren't added to once cleared");
+    }
+
+    #[test]
+    fn picking_up_clears_penalties_and_skips()
+    {
+        let mut table = TableState::new(Arc::new(Rules::default()), card("2S"));
+        assert_eq!((table.penalty, table.active), (2, true));
+
+        apply(&mut table, 0, played(&["2H"]));
+        assert_eq!(table.penalty, 4);
+
+        apply(&mut table, 1, Action::PickedUp(4));
+        assert_eq!((table.penalty, table.skips, table.active), (0, 0, false));
+
+        apply(&mut table, 2, played(&["2D"]));
+        assert_eq!(table.penalty, 2, "a penalty already picked up isn't added to");
+    }
+
+    fn hands(hands: &[&[&str]]) -> Vec<CardSet>
+    {
+        hands.iter().map(|h| h.iter().map(|c| card(c)).collect()).collect()
+    }
+
+    #[test]
+    fn cards_score_by_face_value_and_effect()
+    {
+        let codes = ["3C", "XD", "QH", "JH", "JS", "KD", "8D", "2S", "AH", "JR"];
+        let scores = |rules: &Rules| codes.iter().map(|c| rules.card_score(card(c))).collect::<Vec<i32>>();
+
+        assert_eq!(scores(&Rules::default()), vec![3, 10, 10, 10, 20, 10, 20, 20, 25, 50]);
+        assert_eq!(scores(&with_effects("Q=cover,7=skip,5=penalty:5,3=reverse,X-red=wild")), vec![20, 25, 10, 10, 10, 10, 8, 2, 10, 50]);
+    }
+
+    #[test]
+    fn each_scoring_shares_out_the_points()
+    {
+        let left = hands(&[&[], &["3C", "XD"], &["AH"]]);
+
+        let score = |scoring: Scoring, winner: Option<usize>| Rules { scoring, ..Rules::default() }.score_hand(&left, winner, false);
+
+        assert_eq!(score(Scoring::Classic, Some(0)), vec![0, 13, 25]);
+        assert_eq!(score(Scoring::WinnerTakesAll, Some(0)), vec![38, 0, 0]);
+        assert_eq!(score(Scoring::Wins, Some(0)), vec![1, 0, 0]);
+
+        assert_eq!(score(Scoring::Classic, None), vec![0, 13, 25]);
+        assert_eq!(score(Scoring::WinnerTakesAll, None), vec![0, 0, 0]);
+        assert_eq!(score(Scoring::Wins, None), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn finishing_on_a_penalty_earns_the_bonus()
+    {
+        let left = hands(&[&["3C"], &[]]);
+
+        let score = |scoring: Scoring, on_penalty: bool| {
+            Rules { scoring, penalty_finish_bonus: 5, ..Rules::default() }.score_hand(&left, Some(1), on_penalty)
+        };
+
+        assert_eq!(score(Scoring::Classic, true), vec![3, -5]);
+        assert_eq!(score(Scoring::Classic, false), vec![3, 0]);
+        assert_eq!(score(Scoring::WinnerTakesAll, true), vec![0, 8]);
+        assert_eq!(score(Scoring::Wins, true), vec![0, 6]);
+        assert_eq!(Rules::default().score_hand(&left, Some(1), true), vec![3, 0]);
+    }
+
+    #[test]
+    fn scorings_parse_by_name()
+    {
+        assert_eq!(Scoring::parse("classic"), Ok(Scoring::Classic));
+        assert_eq!(Scoring::parse(" Winner-Takes-All "), Ok(Scoring::WinnerTakesAll));
+        assert_eq!(Scoring::parse("wins"), Ok(Scoring::Wins));
+        assert!(Scoring::parse("most").is_err());
+
+        assert!(Scoring::Classic.lower_is_better());
+        assert!(!Scoring::WinnerTakesAll.lower_is_better());
+        assert!(!Scoring::Wins.lower_is_better());
+    }
+}
+
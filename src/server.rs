@@ -0,0 +1,671 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::advisor::explain;
+use crate::strategy::human::{parse_card, suit_named};
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_ADDRESS : &str = "127.0.0.1:7878";
+
+/// How long a client has for each decision before the bot makes it.
+pub const DEFAULT_MOVE_TIMEOUT : Duration = Duration::from_secs(120);
+
+const MAX_NAME_LENGTH : usize = 16;
+
+/// A connected player. Lines to them go straight to the socket; their moves
+/// arrive on `inbox`, forwarded by the thread reading the socket.
+struct Client
+{
+    name: String,
+    writer: Mutex<TcpStream>,
+    inbox: Mutex<Receiver<String>>
+}
+
+impl Client
+{
+    fn send(&self, line: &str)
+    {
+        // A failed write means the client has gone, which its reader sees.
+        let _ = writeln!(self.writer.lock().unwrap(), "{}", line);
+    }
+}
+
+struct Table
+{
+    seats: Vec<Option<Arc<Client>>>,
+    playing: bool
+}
+
+/// Card codes, each after a space.
+fn codes<I: IntoIterator<Item = Card>>(cards: I) -> String
+{
+    cards.into_iter().map(|c| format!(" {}", c.code())).collect()
+}
+
+fn hand_line(hand: &CardSet) -> String
+{
+    format!("HAND{}", codes(hand))
+}
+
+/// A turn from the log as a protocol line.
+fn turn_line(turn: &Turn) -> String
+{
+    let seat = turn.player.map_or("-".to_string(), |p| p.to_string());
+
+    match &turn.action {
+        Action::First(card) => format!("FIRST {}", card.code()),
+        Action::Played(chain) => format!("PLAYED {}{}", seat, codes(chain.iter().copied())),
+        Action::Nominated(suit) => format!("NOMINATED {} {}", seat, suit.code()),
+        Action::PickedUp(n) => format!("PICKED {} {}", seat, n),
+        Action::Skipped => format!("SKIPPED {}", seat)
+    }
+}
+
+fn state_line(game: &Game) -> String
+{
+    let table = &game.table;
+
+    format!("STATE top={} suit={} penalty={} skips={} reversed={} deck={}",
+            table.top_card.code(),
+            table.nominated.map_or('-', |s| s.code()),
+            table.penalty,
+            table.skips,
+            table.reversed,
+            game.cards_available())
+}
+
+/// Plays a seat on behalf of a client, handing over to `fallback` for the
+/// rest of the hand if they disconnect, or for one decision if they don't
+/// make it within `timeout`.
+struct RemoteStrategy
+{
+    client: Arc<Client>,
+    fallback: Box<dyn Strategy + Send>,
+    connected: bool,
+    timeout: Duration
+}
+
+impl RemoteStrategy
+{
+    /// Rejects anything sent while it wasn't the client's decision.
+    fn drain(&mut self)
+    {
+        loop {
+            match self.client.inbox.lock().unwrap().try_recv() {
+                Ok(line) => self.client.send(&format!("ERROR Not your go: {}", line)),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => { self.connected = false; return; }
+            }
+        }
+    }
+
+    /// The client's next move, split into its command and arguments, or
+    /// `None` once they have gone or `deadline` has passed.
+    fn next_move(&mut self, deadline: Instant) -> Option<(String, String)>
+    {
+        if !self.connected { return None; }
+
+        match self.client.inbox.lock().unwrap().recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => {
+                let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+                Some((command.to_uppercase(), rest.trim().to_string()))
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                self.client.send("ERROR Out of time, so a bot moved for you");
+                None
+            },
+            Err(RecvTimeoutError::Disconnected) => { self.connected = false; None }
+        }
+    }
+}
+
+impl Strategy for RemoteStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>
+    {
+        self.drain();
+
+        self.client.send(&hand_line(hand));
+        self.client.send("GO CHAIN");
+
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            let (command, rest) = match self.next_move(deadline) {
+                Some(m) => m,
+                None => return self.fallback.choose_next(hand, table, log)
+            };
+
+            if command != "PLAY" {
+                self.client.send("INVALID Play cards with PLAY, or PLAY alone to pick up");
+                continue;
+            }
+
+            let parsed : Option<Vec<Card>> = rest.split(|c: char| c == ',' || c.is_whitespace())
+                                                 .filter(|w| !w.is_empty())
+                                                 .map(parse_card)
+                                                 .collect();
+
+            let chain = match parsed {
+                Some(chain) => chain,
+                None => { self.client.send(&format!("INVALID Can't read the cards in '{}'", rest)); continue; }
+            };
+
+            let mut remaining = *hand;
+            let held = chain.iter().all(|card| remaining.remove(card));
+
+            let valid = match chain.is_empty() {
+                true => !can_go(table, hand),
+                false => held && is_valid(table, &chain)
+            };
+
+            if valid { return chain; }
+
+            let why = explain(table, hand, &chain).unwrap_or_else(|| "That can't be played".to_string());
+            self.client.send(&format!("INVALID {}", why));
+        }
+    }
+
+    fn choose_suit(&mut self, hand: &CardSet, log: &[Turn]) -> Suit
+    {
+        self.drain();
+        self.client.send("GO SUIT");
+
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            match self.next_move(deadline) {
+                None => return self.fallback.choose_suit(hand, log),
+                Some((command, rest)) => match suit_named(&rest) {
+                    Some(suit) if command == "SUIT" => return suit,
+                    _ => self.client.send("INVALID Nominate a suit with SUIT S, C, H or D")
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &str { &self.client.name }
+}
+
+/// Hosts tables for clients connecting over TCP, filling any seat without
+/// a client with `bot` when a hand starts.
+pub struct Server
+{
+    tables: Mutex<HashMap<String, Table>>,
+    bot: StrategyType,
+    rules: Rules,
+    move_timeout: Duration
+}
+
+impl Server
+{
+    pub fn new(bot: StrategyType, rules: Rules) -> Server
+    {
+        Server { tables: Mutex::new(HashMap::new()), bot, rules, move_timeout: DEFAULT_MOVE_TIMEOUT }
+    }
+
+    /// How long clients have for each decision before `bot` makes it.
+    pub fn set_move_timeout(&mut self, timeout: Duration)
+    {
+        self.move_timeout = timeout;
+    }
+
+    /// Accepts clients on `listener` for good, serving each on its own
+    /// thread. Failures to accept are passed to `report`.
+    pub fn serve<F>(self, listener: TcpListener, report: F)
+        where F: Fn(String)
+    {
+        let server = Arc::new(self);
+
+        for (id, stream) in listener.incoming().enumerate() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    report(format!("Could not accept a client: {}", e));
+                    // Running out of file handles fails every accept for a while.
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+            };
+
+            // Lines are short and each waits on a reply, so send them at once.
+            let _ = stream.set_nodelay(true);
+            let server = server.clone();
+
+            std::thread::spawn(move || server.connect(stream, id + 1));
+        }
+    }
+
+    fn connect(self: Arc<Self>, stream: TcpStream, id: usize)
+    {
+        let reader = match stream.try_clone() {
+            Ok(s) => BufReader::new(s),
+            Err(_) => return
+        };
+
+        let (moves, inbox) = channel();
+
+        let mut client = Arc::new(Client {
+            name: format!("guest{}", id),
+            writer: Mutex::new(stream),
+            inbox: Mutex::new(inbox)
+        });
+
+        let mut seated : Option<String> = None;
+
+        client.send(&format!("WELCOME {}", client.name));
+
+        for line in reader.lines() {
+
+            let line = match line { Ok(line) => line, Err(_) => break };
+            let words : Vec<&str> = line.split_whitespace().collect();
+            let command = words.first().map_or(String::new(), |w| w.to_uppercase());
+
+            match (command.as_str(), &words[1.min(words.len())..]) {
+                ("NAME", [name]) => match Arc::get_mut(&mut client) {
+                    Some(c) if seated.is_none() => {
+                        c.name = name.chars().take(MAX_NAME_LENGTH).collect();
+                        client.send(&format!("WELCOME {}", client.name));
+                    },
+                    _ => client.send("ERROR Change name before taking a seat")
+                },
+                ("TABLES", []) => {
+                    for line in self.list() { client.send(&line); }
+                    client.send("END");
+                },
+                ("HOST", [table, seats]) if seated.is_none() => match seats.parse::<usize>() {
+                    Ok(n) if (2..=MAX_PLAYERS).contains(&n) => {
+                        match self.host(table, n, &client) {
+                            Ok(()) => seated = Some(table.to_string()),
+                            Err(e) => client.send(&format!("ERROR {}", e))
+                        }
+                    },
+                    _ => client.send(&format!("ERROR A table seats 2 to {} players", MAX_PLAYERS))
+                },
+                ("JOIN", [table, rest @ ..]) if seated.is_none() && rest.len() <= 1 => {
+                    let seat = rest.first().map(|s| s.parse::<usize>().map_err(|_| format!("'{}' is not a seat", s)));
+
+                    match seat.transpose().and_then(|seat| self.join(table, seat, &client)) {
+                        Ok(()) => seated = Some(table.to_string()),
+                        Err(e) => client.send(&format!("ERROR {}", e))
+                    }
+                },
+                ("HOST", _) | ("JOIN", _) if seated.is_some() => client.send("ERROR Leave this table first"),
+                ("LEAVE", []) => match &seated {
+                    Some(table) => match self.leave(table, &client, false) {
+                        Ok(()) => {
+                            client.send(&format!("LEFT {}", table));
+                            seated = None;
+                        },
+                        Err(e) => client.send(&format!("ERROR {}", e))
+                    },
+                    None => client.send("ERROR Not at a table")
+                },
+                ("START", []) => match &seated {
+                    Some(table) => if let Err(e) = self.clone().start(table) { client.send(&format!("ERROR {}", e)) },
+                    None => client.send("ERROR Not at a table")
+                },
+                ("PLAY", _) | ("SUIT", _) if seated.is_some() => { let _ = moves.send(line.clone()); },
+                ("PLAY", _) | ("SUIT", _) => client.send("ERROR Not at a table"),
+                ("QUIT", []) => break,
+                _ => client.send(&format!("ERROR Can't understand '{}'", line))
+            }
+        }
+
+        // Dropping `moves` hands a seat in play over to its fallback.
+        drop(moves);
+
+        if let Some(table) = seated { let _ = self.leave(&table, &client, true); }
+    }
+
+    fn list(&self) -> Vec<String>
+    {
+        let tables = self.tables.lock().unwrap();
+        let mut names : Vec<&String> = tables.keys().collect();
+        names.sort();
+
+        names.into_iter().map(|name| {
+            let table = &tables[name];
+            let taken = table.seats.iter().filter(|s| s.is_some()).count();
+            let status = if table.playing { "playing" } else { "waiting" };
+
+            format!("TABLE {} {}/{} {}", name, taken, table.seats.len(), status)
+        }).collect()
+    }
+
+    /// Opens a table with the client in seat 0.
+    fn host(&self, name: &str, seats: usize, client: &Arc<Client>) -> Result<(), String>
+    {
+        let mut tables = self.tables.lock().unwrap();
+
+        if tables.contains_key(name) { return Err(format!("There is already a table called {}", name)); }
+
+        let mut table = Table { seats: vec![None; seats], playing: false };
+        Server::sit(&mut table, name, Some(0), client)?;
+
+        tables.insert(name.to_string(), table);
+        Ok(())
+    }
+
+    fn join(&self, name: &str, seat: Option<usize>, client: &Arc<Client>) -> Result<(), String>
+    {
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables.get_mut(name).ok_or(format!("There is no table called {}", name))?;
+
+        Server::sit(table, name, seat, client)
+    }
+
+    /// Seats the client at `seat`, or the first free seat, telling the table.
+    fn sit(table: &mut Table, name: &str, seat: Option<usize>, client: &Arc<Client>) -> Result<(), String>
+    {
+        if table.playing { return Err(format!("A hand is being played at {}", name)); }
+
+        let seat = match seat {
+            Some(seat) if seat >= table.seats.len() => return Err(format!("{} has seats 0 to {}", name, table.seats.len() - 1)),
+            Some(seat) if table.seats[seat].is_some() => return Err(format!("Seat {} is taken", seat)),
+            Some(seat) => seat,
+            None => table.seats.iter().position(|s| s.is_none()).ok_or(format!("{} is full", name))?
+        };
+
+        table.seats[seat] = Some(client.clone());
+
+        for other in table.seats.iter().flatten() {
+            other.send(&format!("SEATED {} {} {}", name, seat, client.name));
+        }
+
+        Ok(())
+    }
+
+    /// Frees the client's seat; unless they have `gone`, only between hands.
+    /// The last to leave closes the table.
+    fn leave(&self, name: &str, client: &Arc<Client>, gone: bool) -> Result<(), String>
+    {
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables.get_mut(name).ok_or(format!("There is no table called {}", name))?;
+
+        if table.playing && !gone { return Err("Wait for the hand to finish".to_string()); }
+
+        for seat in table.seats.iter_mut() {
+            if seat.as_ref().is_some_and(|c| Arc::ptr_eq(c, client)) { *seat = None; }
+        }
+
+        if table.seats.iter().all(|s| s.is_none()) && !table.playing { tables.remove(name); }
+
+        Ok(())
+    }
+
+    /// Deals a hand at the table on a new thread.
+    fn start(self: Arc<Self>, name: &str) -> Result<(), String>
+    {
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables.get_mut(name).ok_or(format!("There is no table called {}", name))?;
+
+        if table.playing { return Err(format!("A hand is already being played at {}", name)); }
+
+        table.playing = true;
+
+        let seats = table.seats.clone();
+        let name = name.to_string();
+        drop(tables);
+
+        std::thread::spawn(move || {
+            self.play_hand(&seats);
+
+            let mut tables = self.tables.lock().unwrap();
+
+            if let Some(table) = tables.get_mut(&name) {
+                table.playing = false;
+                if table.seats.iter().all(|s| s.is_none()) { tables.remove(&name); }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Plays one hand, sending each client their own hand and everything
+    /// the whole table sees: the log, the table state and how many cards
+    /// each player holds.
+    fn play_hand(&self, seats: &[Option<Arc<Client>>])
+    {
+        let player_types : Vec<StrategyType> = seats.iter().map(|seat| match seat {
+            None => self.bot.clone(),
+            Some(client) => {
+                let client = client.clone();
+                let bot = self.bot.clone();
+                let timeout = self.move_timeout;
+
+                StrategyType::new("remote", move |players| Box::new(RemoteStrategy {
+                    client: client.clone(),
+                    fallback: make_strategy(&bot, players),
+                    connected: true,
+                    timeout
+                }))
+            }
+        }).collect();
+
+        let clients : Vec<&Arc<Client>> = seats.iter().flatten().collect();
+        let broadcast = |line: &str| for client in &clients { client.send(line); };
+
+        let mut game = Game::with_rules(&player_types, self.rules.clone());
+
+        let names : Vec<String> = game.players.iter().map(|p| p.strategy.name().replace(' ', "_")).collect();
+        broadcast(&format!("PLAYERS {}", names.join(" ")));
+
+        for (seat, client) in seats.iter().enumerate() {
+            if let Some(client) = client {
+                client.send(&format!("SEAT {}", seat));
+                client.send(&hand_line(&game.players[seat].hand));
+            }
+        }
+
+        let mut sent = 0;
+
+        loop {
+            for turn in &game.log[sent..] { broadcast(&turn_line(turn)); }
+            sent = game.log.len();
+
+            if game.decision().is_none() { break; }
+
+            let counts : Vec<String> = game.players.iter().map(|p| p.hand.len().to_string()).collect();
+            broadcast(&format!("COUNTS {}", counts.join(" ")));
+            broadcast(&state_line(&game));
+            broadcast(&format!("TURN {}", game.curr_player_id));
+
+            let seat = game.curr_player_id;
+            game.decide();
+
+            if let Some(client) = &seats[seat] { client.send(&hand_line(&game.players[seat].hand)); }
+        }
+
+        let result = game.result().unwrap();
+
+        for (seat, remaining) in result.remaining.iter().enumerate() {
+            broadcast(&format!("REVEAL {}{}", seat, codes(remaining)));
+        }
+
+        let points : Vec<String> = result.points.iter().map(|p| p.to_string()).collect();
+        let winner = result.winner.map_or("-".to_string(), |w| w.to_string());
+
+        broadcast(&format!("RESULT {} {}", winner, points.join(" ")));
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strategy::registry::Registry;
+
+    use std::convert::TryFrom;
+    use std::sync::mpsc::Sender;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    /// A client on a loopback socket, with the sender feeding its moves and
+    /// the far end of the socket to read what it is sent.
+    fn client(name: &str) -> (Arc<Client>, Sender<String>, BufReader<TcpStream>)
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let far = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (near, _) = listener.accept().unwrap();
+
+        far.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+
+        let (moves, inbox) = channel();
+        let client = Client { name: name.to_string(), writer: Mutex::new(near), inbox: Mutex::new(inbox) };
+
+        (Arc::new(client), moves, BufReader::new(far))
+    }
+
+    fn read(reader: &mut BufReader<TcpStream>) -> String
+    {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    fn remote(client: &Arc<Client>, timeout: Duration) -> RemoteStrategy
+    {
+        let bot = Registry::standard().parse("computer_v1").unwrap();
+
+        RemoteStrategy { client: client.clone(), fallback: make_strategy(&bot, &[]), connected: true, timeout }
+    }
+
+    fn table() -> TableState
+    {
+        let mut table = TableState::new(Arc::new(Rules::default()), card("5S"));
+        table.active = false;
+        table
+    }
+
+    #[test]
+    fn turns_as_lines()
+    {
+        let line = |player, action| turn_line(&Turn { player, action });
+
+        assert_eq!(line(None, Action::First(card("5S"))), "FIRST 5S");
+        assert_eq!(line(Some(1), Action::Played(vec![card("XH"), card("XD")])), "PLAYED 1 XH XD");
+        assert_eq!(line(Some(0), Action::Nominated(Suit::Hearts)), "NOMINATED 0 H");
+        assert_eq!(line(Some(2), Action::PickedUp(3)), "PICKED 2 3");
+        assert_eq!(line(Some(1), Action::Skipped), "SKIPPED 1");
+    }
+
+    #[test]
+    fn hands_and_state_as_lines()
+    {
+        let hand : CardSet = ["JR", "9D"].iter().map(|c| card(c)).collect();
+        let mut words : Vec<String> = hand_line(&hand).split(' ').map(|w| w.to_string()).collect();
+        words[1..].sort();
+
+        assert_eq!(words, vec!["HAND", "9D", "JR"]);
+
+        let bots = vec![Registry::standard().parse("computer_v1").unwrap(); 2];
+        let mut game = Game::with_rules(&bots, Rules::default());
+        game.table = table();
+        game.table.penalty = 2;
+
+        let state = state_line(&game);
+        assert!(state.starts_with("STATE top=5S suit=- penalty=2 skips=0 reversed=false deck="), "{}", state);
+    }
+
+    #[test]
+    fn remote_moves_are_checked()
+    {
+        let (client, moves, mut reader) = client("alice");
+        let mut strategy = remote(&client, DEFAULT_MOVE_TIMEOUT);
+
+        let hand : CardSet = ["5H", "9D"].iter().map(|c| card(c)).collect();
+        let chosen = std::thread::spawn(move || strategy.choose_next(&hand, &table(), &[]));
+
+        assert!(read(&mut reader).starts_with("HAND"));
+        assert_eq!(read(&mut reader), "GO CHAIN");
+
+        for (line, reply) in [
+            ("PLAY 9D", "INVALID"),
+            ("PLAY", "INVALID"),
+            ("PLAY 5H 5H", "INVALID"),
+            ("PLAY ZZ", "INVALID Can't read the cards in 'ZZ'"),
+            ("SUIT S", "INVALID Play cards with PLAY, or PLAY alone to pick up")
+        ] {
+            moves.send(line.to_string()).unwrap();
+            assert!(read(&mut reader).starts_with(reply), "{} should get {}", line, reply);
+        }
+
+        moves.send("play 5h".to_string()).unwrap();
+        assert_eq!(chosen.join().unwrap(), vec![card("5H")]);
+    }
+
+    #[test]
+    fn moves_out_of_turn_are_refused()
+    {
+        let (client, moves, mut reader) = client("alice");
+        let mut strategy = remote(&client, DEFAULT_MOVE_TIMEOUT);
+
+        moves.send("SUIT H".to_string()).unwrap();
+
+        let chosen = std::thread::spawn(move || strategy.choose_suit(&CardSet::default(), &[]));
+
+        assert_eq!(read(&mut reader), "ERROR Not your go: SUIT H");
+        assert_eq!(read(&mut reader), "GO SUIT");
+
+        moves.send("SUIT D".to_string()).unwrap();
+        assert_eq!(chosen.join().unwrap(), Suit::Diamonds);
+    }
+
+    #[test]
+    fn the_bot_moves_for_slow_or_departed_clients()
+    {
+        let hand : CardSet = ["5H", "9D"].iter().map(|c| card(c)).collect();
+
+        let (client, moves, mut reader) = client("alice");
+        let mut strategy = remote(&client, Duration::from_millis(50));
+
+        assert_eq!(strategy.choose_next(&hand, &table(), &[]), vec![card("5H")]);
+        assert!(strategy.connected);
+
+        read(&mut reader);
+        read(&mut reader);
+        assert_eq!(read(&mut reader), "ERROR Out of time, so a bot moved for you");
+
+        drop(moves);
+        strategy.timeout = DEFAULT_MOVE_TIMEOUT;
+
+        assert_eq!(strategy.choose_next(&hand, &table(), &[]), vec![card("5H")]);
+        assert!(!strategy.connected);
+    }
+
+    #[test]
+    fn hosts_are_seated_with_their_table()
+    {
+        let bot = Registry::standard().parse("computer_v1").unwrap();
+        let server = Server::new(bot, Rules::default());
+
+        let (alice, _a, mut alice_reader) = client("alice");
+        let (bob, _b, mut bob_reader) = client("bob");
+        let (carol, _c, _) = client("carol");
+
+        assert_eq!(server.host("t", 2, &alice), Ok(()));
+        assert_eq!(read(&mut alice_reader), "SEATED t 0 alice");
+        assert_eq!(server.list(), vec!["TABLE t 1/2 waiting"]);
+
+        assert!(server.host("t", 3, &bob).is_err());
+        assert!(server.join("t", Some(0), &bob).is_err());
+        assert_eq!(server.join("t", None, &bob), Ok(()));
+        assert_eq!(read(&mut bob_reader), "SEATED t 1 bob");
+        assert!(server.join("t", None, &carol).is_err());
+
+        assert_eq!(server.leave("t", &alice, false), Ok(()));
+        assert_eq!(server.leave("t", &bob, false), Ok(()));
+        assert!(server.list().is_empty());
+    }
+}
@@ -1,37 +1,69 @@
 pub mod human;
 pub mod computer_v1;
 pub mod computer_v2;
+pub mod heuristic;
+pub mod neural;
+pub mod baseline;
+pub mod cfr;
+pub mod advisor;
+pub mod registry;
+pub mod shared;
 
-mod shared;
-
-use std::collections::HashSet;
+use std::sync::Arc;
 
+use crate::rules::*;
 use crate::cards::*;
 use crate::game::*;
 
-use human::*;
-use computer_v1::*;
-use computer_v2::*;
-
 pub trait Strategy {
-    fn choose_next(&mut self, hand: &HashSet<Card>, log: &[Turn]) -> Vec<Card>;
-    fn choose_suit(&mut self, hand: &HashSet<Card>, log: &[Turn]) -> Suit;
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>;
+    fn choose_suit(&mut self, hand: &CardSet, log: &[Turn]) -> Suit;
     fn name(&self) -> &str;
+
+    /// Reseeds whatever the strategy draws at random, so a seeded game
+    /// plays out the same every time.
+    fn seed(&mut self, _: u64) {}
+
+    /// How highly the strategy rates playing `chain`, for those that pick
+    /// the best scoring chain; `None` for those that choose another way.
+    fn score(&self, _: &CardSet, _: &TableState, _: &[Card]) -> Option<f32> { None }
 }
 
-#[derive(Clone,Copy,Debug)]
-pub enum StrategyType
+pub type StrategyFactory = dyn Fn(&[StrategyType]) -> Box<dyn Strategy + Send> + Send + Sync;
+
+/// A recipe for seating a strategy: the spec it was built from (e.g.
+/// `heuristic:pickup_weight=12`) and a factory producing a fresh instance
+/// for each game. Build these through a `registry::Registry`.
+#[derive(Clone)]
+pub struct StrategyType
 {
-    ComputerV1,
-    ComputerV2,
-    Human
+    pub spec: String,
+    factory: Arc<StrategyFactory>
 }
 
-pub fn make_strategy<'a>(t: &StrategyType, players: &[StrategyType]) -> Box<dyn Strategy + Send>
+impl StrategyType
 {
-    match t {
-        StrategyType::ComputerV1 => Box::new(ComputerStrategyV1 {}),
-        StrategyType::ComputerV2 => Box::new(ComputerStrategyV2::new(players)),
-        StrategyType::Human => Box::new(HumanStrategy {}),
+    pub fn new<F>(spec: &str, factory: F) -> StrategyType
+    where
+        F : Fn(&[StrategyType]) -> Box<dyn Strategy + Send> + Send + Sync + 'static
+    {
+        StrategyType { spec: spec.to_string(), factory: Arc::new(factory) }
+    }
+
+    /// Parses a spec against the standard registry.
+    pub fn parse(spec: &str) -> Result<StrategyType, String>
+    {
+        registry::Registry::standard().parse(spec)
     }
-}
\ No newline at end of file
+}
+
+impl std::fmt::Debug for StrategyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+pub fn make_strategy(t: &StrategyType, players: &[StrategyType]) -> Box<dyn Strategy + Send>
+{
+    (t.factory)(players)
+}
@@ -0,0 +1,243 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+
+/// Chains best first, each with its score if the bot scores chains.
+pub type Ranking = Vec<(Vec<Card>, Option<f32>)>;
+
+/// Ranks the legal chains the way a bot would.
+pub struct Advisor
+{
+    pub spec: String,
+    strategy: Box<dyn Strategy + Send>
+}
+
+impl Advisor
+{
+    /// `players` are the seats of the game the bot advises in.
+    pub fn new(bot: &StrategyType, players: &[StrategyType]) -> Advisor
+    {
+        Advisor { spec: bot.spec.clone(), strategy: make_strategy(bot, players) }
+    }
+
+    /// The chain the bot would play.
+    pub fn play(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>
+    {
+        self.strategy.choose_next(hand, table, log)
+    }
+
+    /// Every chain the bot would consider, best first, with its score, and
+    /// whether the search behind it was complete. A bot that doesn't score
+    /// chains only offers the one it would play, unscored.
+    pub fn rank(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> (Ranking, shared::Enumeration)
+    {
+        let strategy = &self.strategy;
+        let mut ranked = Vec::new();
+        let mut unscored = false;
+
+        let searched = shared::iterate_valid_chains(table, hand, &mut |chain: &[Card]| {
+            match strategy.score(hand, table, chain) {
+                Some(score) if score > f32::NEG_INFINITY => ranked.push((chain.to_vec(), Some(score))),
+                Some(_) => (),
+                None => unscored = true
+            }
+        });
+
+        if unscored {
+            let chain = self.play(hand, table, log);
+            return (if chain.is_empty() { Vec::new() } else { vec![(chain, None)] }, shared::Enumeration::Complete);
+        }
+
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        (ranked, searched)
+    }
+
+    /// The best `count` chains, one per line, with the penalty each leaves
+    /// the next player.
+    pub fn hint(&mut self, hand: &CardSet, table: &TableState, log: &[Turn], count: usize) -> String
+    {
+        let (ranked, searched) = self.rank(hand, table, log);
+
+        if ranked.is_empty() {
+            return match table.penalty {
+                0 => "Nothing goes; pick up.\n".to_string(),
+                n => format!("Nothing goes; pick up {}.\n", n)
+            };
+        }
+
+        let mut s = format!("{} would play:\n", self.spec);
+
+        for (i, (chain, score)) in ranked.iter().take(count).enumerate() {
+            let mut notes = Vec::new();
+
+            if let Some(score) = score { notes.push(format!("scores {:.1}", score)); }
+
+            match penalty_passed_on(table, chain) {
+                0 => (),
+                n => notes.push(format!("passes on {}", n))
+            }
+
+            s += &format!("{:>3}. {:?}", i + 1, chain);
+            if !notes.is_empty() { s += &format!(" ({})", notes.join(", ")); }
+            s += "\n";
+        }
+
+        if ranked.len() > count { s += &format!("     ... and {} more\n", ranked.len() - count); }
+        if searched == shared::Enumeration::Truncated { s += "     (too many chains to search them all; longer ones may be missing)\n"; }
+
+        s
+    }
+}
+
+/// The penalty the next player faces once `chain` is played.
+pub fn penalty_passed_on(table: &TableState, chain: &[Card]) -> usize
+{
+    let mut after = table.clone();
+    after.apply(&Turn { player: None, action: Action::Played(chain.to_vec()) });
+    after.penalty
+}
+
+/// Why `next` can't start a chain on `table`.
+fn why_not_follow(table: &TableState, next: Card) -> String
+{
+    let rules = &table.rules;
+    let top = table.top_card;
+
+    if let Some(suit) = table.nominated {
+        return format!("{:?} was nominated, so {:?} must be a {:?} or wild.", suit, next, suit);
+    }
+
+    if top.is_joker() {
+        return format!("The joker's penalty of {} can only be passed on with a penalty card.", table.penalty);
+    }
+
+    match (table.active, rules.effect(top)) {
+        (true, Some(Effect::Penalty(_))) => format!(
+            "{:?} passes on a penalty of {}: answer with a {:?} or a penalty card of the same suit no bigger than it, or pick up.",
+            top, table.penalty, top.rank()),
+        (true, Some(Effect::Skip)) => format!(
+            "{:?} is a skip: answer with a {:?} or another skip card, or miss your go.", top, top.rank()),
+        _ => format!("{:?} matches neither the suit nor the rank of {:?}.", next, top)
+    }
+}
+
+/// Why `next` can't follow `prev` in a chain.
+fn why_not_link(rules: &Rules, prev: Card, next: Card) -> String
+{
+    let wild = |c: Card| rules.effect(c) == Some(Effect::Wild);
+
+    if prev.is_joker() || next.is_joker() {
+        format!("The joker can't stand in for a card that links to both its neighbours before {:?}.", next)
+    } else if wild(prev) != wild(next) {
+        format!("{:?} can't follow {:?}: wild cards only link to wild cards.", next, prev)
+    } else {
+        format!("{:?} can't follow {:?}: they don't share a rank and aren't next to each other in the same suit.", next, prev)
+    }
+}
+
+/// Why `chain` can't be played from `hand`, or `None` if it can.
+pub fn explain(table: &TableState, hand: &CardSet, chain: &[Card]) -> Option<String>
+{
+    if chain.is_empty() {
+        return if can_go(table, hand) { Some("You have a card that goes, so you must play.".to_string()) } else { None };
+    }
+
+    let mut remaining = *hand;
+
+    if let Some(missing) = chain.iter().find(|card| !remaining.remove(card)) {
+        return Some(format!("You don't have {}{:?}.", if hand.contains(missing) { "another " } else { "" }, missing));
+    }
+
+    if !can_follow(table, chain[0]) { return Some(why_not_follow(table, chain[0])); }
+
+    let mut ends = chain_ends(&table.rules, None, chain[0]);
+
+    for (i, next) in chain.iter().enumerate().skip(1) {
+        ends = chain_ends(&table.rules, Some(&ends), *next);
+        if ends.is_empty() { return Some(why_not_link(&table.rules, chain[i - 1], *next)); }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strategy::registry::Registry;
+
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    fn cards(codes: &[&str]) -> Vec<Card>
+    {
+        codes.iter().map(|c| Card::try_from(*c).unwrap()).collect()
+    }
+
+    fn hand(codes: &[&str]) -> CardSet
+    {
+        cards(codes).into_iter().collect()
+    }
+
+    fn table(top: &str) -> TableState
+    {
+        TableState::new(Arc::new(Rules::default()), Card::try_from(top).unwrap())
+    }
+
+    fn advisor(spec: &str) -> Advisor
+    {
+        let bot = Registry::standard().parse(spec).unwrap();
+        Advisor::new(&bot, &[bot.clone(), bot.clone()])
+    }
+
+    #[test]
+    fn chains_are_ranked_best_first_from_what_the_bot_plays()
+    {
+        let (held, on) = (hand(&["6S", "7S", "KH", "9D"]), table("5S"));
+
+        for spec in ["computer_v1", "heuristic:pickup_weight=3", "longest_chain", "dump_highest"] {
+            let mut advisor = advisor(spec);
+            let (ranked, searched) = advisor.rank(&held, &on, &[]);
+
+            assert_eq!(searched, shared::Enumeration::Complete);
+            assert_eq!(ranked[0].0, advisor.play(&held, &on, &[]), "{}", spec);
+            assert!(ranked.iter().all(|(chain, _)| explain(&on, &held, chain).is_none()));
+            assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1), "{} ranked out of order", spec);
+        }
+    }
+
+    #[test]
+    fn bots_that_dont_score_offer_the_chain_they_would_play()
+    {
+        let (held, on) = (hand(&["6S", "7S", "KH", "9D"]), table("5S"));
+        let (ranked, _) = advisor("random").rank(&held, &on, &[]);
+
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].1, None);
+        assert!(explain(&on, &held, &ranked[0].0).is_none());
+
+        let hint = advisor("random").hint(&held, &on, &[], 5);
+        assert!(hint.starts_with("random would play:\n  1. "), "{}", hint);
+        assert!(!hint.contains("scores"), "{}", hint);
+    }
+
+    #[test]
+    fn hints_list_the_best_chains_and_the_penalties_they_pass_on()
+    {
+        let (held, on) = (hand(&["6S", "7S", "KH", "9D"]), table("5S"));
+        let hint = advisor("longest_chain").hint(&held, &on, &[], 1);
+        let lines : Vec<&str> = hint.lines().collect();
+
+        assert_eq!(lines[0], "longest_chain would play:");
+        assert!(lines[1].starts_with("  1. [⟨6♠⟩, ⟨7♠⟩] (scores ") || lines[1].starts_with("  1. [⟨7♠⟩, ⟨6♠⟩] (scores "), "{}", hint);
+        assert!(lines[2].starts_with("     ... and "), "{}", hint);
+
+        let hint = advisor("defender").hint(&hand(&["2S", "KH"]), &table("2D"), &[], 5);
+        assert!(hint.contains("passes on 4)"), "{}", hint);
+
+        let mut bot = advisor("computer_v1");
+        assert_eq!(bot.hint(&hand(&["KH", "5D"]), &table("2S"), &[], 5), "Nothing goes; pick up 2.\n");
+        assert_eq!(bot.hint(&hand(&["KH"]), &table("5S"), &[], 5), "Nothing goes; pick up.\n");
+    }
+}
@@ -0,0 +1,275 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+/// Plays a uniformly random legal chain and nominates a random suit.
+pub struct RandomStrategy
+{
+    rng: StdRng
+}
+
+impl RandomStrategy
+{
+    pub fn strategy_type(spec: &str) -> StrategyType
+    {
+        StrategyType::new(spec, |_| Box::new(RandomStrategy { rng: StdRng::from_rng(thread_rng()).unwrap() }))
+    }
+
+    /// A chain built from a random card that goes, adding a random card
+    /// that follows until it picks stopping, which is as likely as any one
+    /// card. Not uniform over chains, but it can reach any of them.
+    fn random_walk(&mut self, hand: &CardSet, table: &TableState) -> Vec<Card>
+    {
+        let rules = &table.rules;
+        let starts : Vec<Card> = hand.distinct().iter().filter(|c| can_follow(table, *c)).collect();
+
+        let mut chain = match starts.choose(&mut self.rng) {
+            Some(first) => vec![*first],
+            None => return Vec::new()
+        };
+
+        let mut remaining = *hand;
+        remaining.remove(&chain[0]);
+        let mut ends = chain_ends(rules, None, chain[0]);
+
+        loop {
+            let follows = ends.iter().fold(CardSet::new(), |set, end| set | rules.links(end));
+            let options : Vec<Card> = (follows & remaining).distinct().iter().collect();
+
+            let i = self.rng.gen_range(0..=options.len());
+            if i == options.len() { return chain; }
+
+            remaining.remove(&options[i]);
+            ends = chain_ends(rules, Some(&ends), options[i]);
+            chain.push(options[i]);
+        }
+    }
+}
+
+impl Strategy for RandomStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, _: &[Turn]) -> Vec<Card>
+    {
+        let rng = &mut self.rng;
+        let mut chosen = Vec::<Card>::new();
+        let mut seen = 0;
+
+        let searched = shared::iterate_valid_chains(table, hand, &mut |chain: &[Card]| {
+            seen += 1;
+            if rng.gen_range(0..seen) == 0 {
+                chosen.clear();
+                chosen.extend_from_slice(chain);
+            }
+        });
+
+        match searched {
+            shared::Enumeration::Complete => chosen,
+            // The search only reached chains growing from the first few
+            // cards, so walk out from any of them instead.
+            shared::Enumeration::Truncated => self.random_walk(hand, table)
+        }
+    }
+
+    fn choose_suit(&mut self, _: &CardSet, _: &[Turn]) -> Suit
+    {
+        *Suit::all().choose(&mut self.rng).unwrap()
+    }
+
+    fn name(&self) -> &str { "Random" }
+
+    fn seed(&mut self, seed: u64)
+    {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+/// A greedy bot defined entirely by how it scores a chain, given the table
+/// it is played onto.
+pub struct RuleOfThumbStrategy
+{
+    name: &'static str,
+    score: fn(&TableState, &[Card]) -> f32
+}
+
+impl RuleOfThumbStrategy
+{
+    pub fn strategy_type(spec: &str, name: &'static str, score: fn(&TableState, &[Card]) -> f32) -> StrategyType
+    {
+        StrategyType::new(spec, move |_| Box::new(RuleOfThumbStrategy { name, score }))
+    }
+}
+
+impl Strategy for RuleOfThumbStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, _: &[Turn]) -> Vec<Card>
+    {
+        let score = self.score;
+
+        shared::find_best_valid(table, hand, |chain| score(table, chain))
+    }
+
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
+    {
+        shared::choose_suit(hand)
+    }
+
+    fn name(&self) -> &str { self.name }
+
+    fn score(&self, _: &CardSet, table: &TableState, chain: &[Card]) -> Option<f32>
+    {
+        Some((self.score)(table, chain))
+    }
+}
+
+fn total_score(rules: &Rules, chain: &[Card]) -> f32
+{
+    chain.iter().map(|c| rules.card_score(*c)).sum::<i32>() as f32
+}
+
+/// Plays as many cards as possible, preferring high scoring ones on ties.
+pub fn longest_chain_score(table: &TableState, chain: &[Card]) -> f32
+{
+    chain.len() as f32 * 1000.0 + total_score(&table.rules, chain)
+}
+
+/// Plays the single highest scoring card.
+pub fn dump_highest_score(table: &TableState, chain: &[Card]) -> f32
+{
+    if chain.len() > 1 { return f32::NEG_INFINITY; }
+    total_score(&table.rules, chain)
+}
+
+/// Passes on as much penalty as possible when facing one, otherwise holds
+/// penalty cards back for defence.
+pub fn defender_score(table: &TableState, chain: &[Card]) -> f32
+{
+    let passed_on = chain.iter().map(|c| table.rules.penalty_value(*c)).sum::<usize>();
+
+    if table.penalty > 0 {
+        passed_on as f32 * 1000.0 + total_score(&table.rules, chain)
+    } else {
+        total_score(&table.rules, chain) - chain.iter().filter(|c| shared::is_defensive(&table.rules, **c)).count() as f32 * 1000.0
+    }
+}
+
+/// Plays like `computer_v1` but only gives up a wild card, an ace in the
+/// standard game, when nothing else goes.
+pub fn ace_hoarder_score(table: &TableState, chain: &[Card]) -> f32
+{
+    shared::score(table, chain)
+        - chain.iter().filter(|c| table.rules.effect(**c) == Some(Effect::Wild)).count() as f32 * 1000.0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strategy::registry::Registry;
+
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    fn cards(codes: &[&str]) -> Vec<Card>
+    {
+        codes.iter().map(|c| Card::try_from(*c).unwrap()).collect()
+    }
+
+    /// `top` face up, its effect already answered unless `active`.
+    fn table(top: &str, active: bool) -> TableState
+    {
+        let mut table = TableState::new(Arc::new(Rules::default()), Card::try_from(top).unwrap());
+        table.active = active;
+        if !active { table.penalty = 0; }
+        table
+    }
+
+    /// The chain the registered bot `spec` plays from `hand`, sorted.
+    fn plays(spec: &str, hand: &[&str], table: &TableState) -> Vec<Card>
+    {
+        let bot = Registry::standard().parse(spec).unwrap();
+        let hand : CardSet = cards(hand).into_iter().collect();
+
+        let mut chain = make_strategy(&bot, &[]).choose_next(&hand, table, &[]);
+        chain.sort();
+        chain
+    }
+
+    fn sorted(codes: &[&str]) -> Vec<Card>
+    {
+        let mut chain = cards(codes);
+        chain.sort();
+        chain
+    }
+
+    #[test]
+    fn random_plays_a_legal_chain_repeatably_once_seeded()
+    {
+        let table = table("5S", false);
+        let hand : CardSet = cards(&["5H", "6H", "7H", "9S", "5D", "3C"]).into_iter().collect();
+
+        let choices : Vec<Vec<Card>> = (0..40).map(|seed| {
+            let mut strategy = RandomStrategy { rng: StdRng::seed_from_u64(0) };
+            strategy.seed(seed);
+            strategy.choose_next(&hand, &table, &[])
+        }).collect();
+
+        assert!(choices.iter().all(|chain| is_valid(&table, chain)));
+        assert!(choices.iter().skip(1).any(|chain| *chain != choices[0]), "40 seeds all chose {:?}", choices[0]);
+
+        let mut again = RandomStrategy { rng: StdRng::seed_from_u64(0) };
+        again.seed(7);
+        assert_eq!(again.choose_next(&hand, &table, &[]), choices[7]);
+    }
+
+    #[test]
+    fn longest_chain_plays_the_most_cards()
+    {
+        assert_eq!(plays("longest_chain", &["5H", "6H", "7H", "9S"], &table("5S", false)), sorted(&["5H", "6H", "7H"]));
+    }
+
+    #[test]
+    fn dump_highest_plays_one_high_card()
+    {
+        assert_eq!(plays("dump_highest", &["5H", "6H", "9S", "4S"], &table("5S", false)), cards(&["9S"]));
+    }
+
+    #[test]
+    fn defender_passes_penalties_on_and_otherwise_keeps_them()
+    {
+        assert_eq!(plays("defender", &["2H", "2D", "9S"], &table("2S", true)), sorted(&["2H", "2D"]));
+
+        let chain = plays("defender", &["5H", "2S", "9S"], &table("5S", false));
+        assert!(!chain.is_empty() && !chain.contains(&cards(&["2S"])[0]), "{:?}", chain);
+    }
+
+    #[test]
+    fn ace_hoarder_only_plays_a_wild_card_when_nothing_else_goes()
+    {
+        assert_eq!(plays("ace_hoarder", &["AS", "9S"], &table("5S", false)), cards(&["9S"]));
+        assert_eq!(plays("ace_hoarder", &["AH", "4D"], &table("5S", false)), cards(&["AH"]));
+    }
+
+    #[test]
+    fn random_walks_are_valid_chains()
+    {
+        let rules = Arc::new(Rules::default());
+        let table = TableState::new(rules.clone(), Card::try_from("5S").unwrap());
+        let hand = rules.deck(1);
+
+        let mut strategy = RandomStrategy { rng: StdRng::seed_from_u64(1) };
+        let mut longest = 0;
+
+        for _ in 0..200 {
+            let chain = strategy.random_walk(&hand, &table);
+            assert!(is_valid(&table, &chain), "{:?}", chain);
+            longest = longest.max(chain.len());
+        }
+
+        assert!(longest > 3);
+    }
+}
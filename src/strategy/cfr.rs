@@ -0,0 +1,617 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub const MOVES : usize = 6;
+
+/// The abstract moves the two-player solver chooses between. Each stands
+/// for one concrete chain in a position: picking up when nothing goes, the
+/// chain `computer_v1` would play, the longest chain, the lowest scoring
+/// single card, the best chain ending on a penalty card and the best chain
+/// holding back penalty and wild cards. Not every move exists everywhere.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move
+{
+    PickUp,
+    Greedy,
+    Longest,
+    Single,
+    Attack,
+    Hold
+}
+
+impl Move
+{
+    pub fn all() -> [Move; MOVES]
+    {
+        [Move::PickUp, Move::Greedy, Move::Longest, Move::Single, Move::Attack, Move::Hold]
+    }
+
+    pub fn index(&self) -> usize
+    {
+        *self as usize
+    }
+}
+
+fn is_special(rules: &Rules, card: Card) -> bool
+{
+    rules.penalty_value(card) > 0 || rules.effect(card) == Some(Effect::Wild)
+}
+
+/// How `move_chains` ranks the chains `m` could stand for; chains it can't
+/// stand for score negative infinity.
+fn move_score(table: &TableState, m: Move, chain: &[Card]) -> f32
+{
+    let score = shared::score(table, chain);
+    let rules = &table.rules;
+
+    match m {
+        Move::PickUp => f32::NEG_INFINITY,
+        Move::Greedy => score,
+        Move::Longest => chain.len() as f32 * 1000.0 + score,
+        Move::Single if chain.len() == 1 => -score,
+        Move::Attack if rules.penalty_value(*chain.last().unwrap()) > 0 => score,
+        Move::Hold if !chain.iter().any(|c| is_special(rules, *c)) => score,
+        _ => f32::NEG_INFINITY
+    }
+}
+
+/// The chain each move stands for from `hand`, where it has one.
+pub fn move_chains(table: &TableState, hand: &CardSet) -> [Option<Vec<Card>>; MOVES]
+{
+    let mut best : [Option<(f32, Vec<Card>)>; MOVES] = Default::default();
+
+    let searched = shared::iterate_valid_chains(table, hand, &mut |chain: &[Card]| {
+        for m in Move::all() {
+            let score = move_score(table, m, chain);
+            let slot = &mut best[m.index()];
+
+            if score > f32::NEG_INFINITY && slot.as_ref().is_none_or(|(s, _)| score > *s) {
+                *slot = Some((score, chain.to_vec()));
+            }
+        }
+    });
+
+    let mut chains = best.map(|b| b.map(|(_, chain)| chain));
+
+    if searched == shared::Enumeration::Truncated {
+        for (m, chain) in Move::all().iter().zip(chains.iter_mut()) {
+            if let Some(c) = chain.take() {
+                *chain = Some(shared::extend_greedily(table, hand, c, |c| move_score(table, *m, c)));
+            }
+        }
+    }
+
+    if chains.iter().all(|c| c.is_none()) { chains[Move::PickUp.index()] = Some(Vec::new()); }
+
+    chains
+}
+
+/// 0 to 3 exactly, then 4-5, 6-8 and 9 or more.
+fn bucket(n: usize) -> u8
+{
+    match n {
+        0..=3 => n as u8,
+        4 | 5 => 4,
+        6..=8 => 5,
+        _ => 6
+    }
+}
+
+/// What the solver knows when choosing: bucketed sizes of its own and its
+/// opponent's hands, the penalty to answer, what the top card demands (0
+/// nothing special, 1 a penalty, 2 a skip, 3 a nominated suit), how many of
+/// its cards could start a chain (up to 3) and how many penalty or wild cards
+/// it holds (up to 2).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InfoSet
+{
+    pub hand: u8,
+    pub opponent: u8,
+    pub penalty: u8,
+    pub top: u8,
+    pub playable: u8,
+    pub specials: u8
+}
+
+impl InfoSet
+{
+    pub fn new(table: &TableState, hand: &CardSet, opponent_cards: usize) -> InfoSet
+    {
+        let top = if table.nominated.is_some() { 3 }
+                  else if table.penalty > 0 { 1 }
+                  else if table.skips > 0 && table.active { 2 }
+                  else { 0 };
+
+        InfoSet {
+            hand: bucket(hand.len()),
+            opponent: bucket(opponent_cards),
+            penalty: bucket(table.penalty),
+            top,
+            playable: hand.distinct().iter().filter(|c| can_follow(table, *c)).count().min(3) as u8,
+            specials: hand.iter().filter(|c| is_special(&table.rules, *c)).count().min(2) as u8
+        }
+    }
+
+    fn fields(&self) -> [u8; 6]
+    {
+        [self.hand, self.opponent, self.penalty, self.top, self.playable, self.specials]
+    }
+}
+
+/// The smallest hand among the other players, worked out from the log as
+/// strategies aren't shown the other hands. `hand` is the player's own.
+pub fn opponent_cards(hand: &CardSet, log: &[Turn]) -> usize
+{
+    let mut sizes : Vec<usize> = Vec::new();
+
+    for turn in log {
+        if let Some(p) = turn.player {
+            if sizes.len() <= p { sizes.resize(p + 1, STARTING_CARD_COUNT); }
+
+            match &turn.action {
+                Action::Played(chain) => sizes[p] -= chain.len(),
+                Action::PickedUp(n) => sizes[p] += n,
+                _ => ()
+            }
+        }
+    }
+
+    if let Some(own) = sizes.iter().position(|s| *s == hand.len()) { sizes.remove(own); }
+
+    sizes.into_iter().min().unwrap_or(STARTING_CARD_COUNT)
+}
+
+fn normalise(weights: &[f64; MOVES], legal: &[bool; MOVES]) -> [f64; MOVES]
+{
+    let total : f64 = (0..MOVES).filter(|m| legal[*m]).map(|m| weights[m].max(0.0)).sum();
+    let count = legal.iter().filter(|l| **l).count() as f64;
+
+    let mut p = [0.0; MOVES];
+
+    for m in (0..MOVES).filter(|m| legal[*m]) {
+        p[m] = if total > 0.0 { weights[m].max(0.0) / total } else { 1.0 / count };
+    }
+
+    p
+}
+
+fn sample<R: Rng>(p: &[f64; MOVES], rng: &mut R) -> usize
+{
+    let mut x = rng.gen::<f64>();
+
+    for (m, pm) in p.iter().enumerate() {
+        if *pm > 0.0 && x < *pm { return m; }
+        x -= pm;
+    }
+
+    (0..MOVES).rev().find(|m| p[*m] > 0.0).unwrap()
+}
+
+/// The average strategy CFR converges on, by information set.
+///
+/// Saved as one line per information set: its six fields, then the
+/// probability of each move in `Move::all()` order.
+#[derive(Clone, Debug, Default)]
+pub struct Policy
+{
+    pub table: HashMap<InfoSet, [f64; MOVES]>
+}
+
+impl Policy
+{
+    pub fn load(path: &str) -> Result<Policy, String>
+    {
+        let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {}: {}", path, e))?;
+
+        let mut table = HashMap::new();
+
+        for line in text.lines().filter(|l| !l.trim_start().starts_with('#') && !l.trim().is_empty()) {
+
+            let fields : Vec<&str> = line.split_whitespace().collect();
+
+            if fields.len() != 6 + MOVES { return Err(format!("Expected {} fields in '{}'", 6 + MOVES, line)); }
+
+            let key : Vec<u8> = fields[..6]
+                                .iter()
+                                .map(|f| f.parse().map_err(|_| format!("Invalid field '{}'", f)))
+                                .collect::<Result<_, String>>()?;
+
+            let mut p = [0.0; MOVES];
+            for (pm, f) in p.iter_mut().zip(&fields[6..]) {
+                *pm = f.parse().map_err(|_| format!("Invalid probability '{}'", f))?;
+            }
+
+            table.insert(InfoSet {
+                hand: key[0], opponent: key[1], penalty: key[2], top: key[3], playable: key[4], specials: key[5]
+            }, p);
+        }
+
+        Ok(Policy { table })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String>
+    {
+        let mut text = String::from("# hand opponent penalty top playable specials | pickup greedy longest single attack hold\n");
+
+        for (key, p) in &self.table {
+            let fields : Vec<String> = key.fields().iter().map(|f| f.to_string())
+                                        .chain(p.iter().map(|x| format!("{:.4}", x)))
+                                        .collect();
+            text += &fields.join(" ");
+            text += "\n";
+        }
+
+        std::fs::write(path, text).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    /// The probability of each legal move, or `None` for a position the
+    /// solver never reached.
+    pub fn probabilities(&self, key: &InfoSet, legal: &[bool; MOVES]) -> Option<[f64; MOVES]>
+    {
+        let weights = self.table.get(key)?;
+
+        if (0..MOVES).all(|m| !legal[m] || weights[m] <= 0.0) { return None; }
+
+        Some(normalise(weights, legal))
+    }
+}
+
+/// Plays a CFR policy, falling back on `computer_v1`'s chain in positions
+/// the policy doesn't cover.
+pub struct CfrStrategy
+{
+    policy: Arc<Policy>,
+    rng: StdRng
+}
+
+impl CfrStrategy
+{
+    pub fn new(policy: Arc<Policy>) -> CfrStrategy
+    {
+        CfrStrategy { policy, rng: StdRng::from_rng(thread_rng()).unwrap() }
+    }
+
+    pub fn strategy_type(spec: &str, path: &str) -> Result<StrategyType, String>
+    {
+        Ok(CfrStrategy::with_policy(spec, Policy::load(path)?))
+    }
+
+    pub fn with_policy(spec: &str, policy: Policy) -> StrategyType
+    {
+        let policy = Arc::new(policy);
+
+        StrategyType::new(spec, move |_| Box::new(CfrStrategy::new(policy.clone())))
+    }
+}
+
+pub fn legal_moves(chains: &[Option<Vec<Card>>; MOVES]) -> [bool; MOVES]
+{
+    let mut legal = [false; MOVES];
+    for m in 0..MOVES { legal[m] = chains[m].is_some(); }
+    legal
+}
+
+impl Strategy for CfrStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>
+    {
+        let mut chains = move_chains(table, hand);
+        let legal = legal_moves(&chains);
+        let key = InfoSet::new(table, hand, opponent_cards(hand, log));
+
+        let m = match self.policy.probabilities(&key, &legal) {
+            Some(p) => sample(&p, &mut self.rng),
+            None if legal[Move::Greedy.index()] => Move::Greedy.index(),
+            None => Move::PickUp.index()
+        };
+
+        chains[m].take().unwrap()
+    }
+
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
+    {
+        shared::choose_suit(hand)
+    }
+
+    fn name(&self) -> &str { "CFR" }
+
+    fn seed(&mut self, seed: u64)
+    {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+#[derive(Clone, Default)]
+struct Node
+{
+    regrets: [f64; MOVES],
+    average: [f64; MOVES]
+}
+
+/// Outcome-sampling Monte Carlo CFR over the two-player game, played out in
+/// `Game` with the chains abstracted to `Move`s and the positions to
+/// `InfoSet`s. Each iteration samples one hand, exploring with probability
+/// `EXPLORATION` at the traversing player's decisions. Suits are nominated
+/// as `computer_v1` would.
+pub struct CfrTrainer
+{
+    nodes: HashMap<InfoSet, Node>,
+    rules: Rules,
+    players: Vec<StrategyType>,
+    rng: StdRng,
+    pub iterations: usize
+}
+
+const EXPLORATION : f64 = 0.6;
+
+/// Seated where the moves are chosen outside the strategy, which is only
+/// asked to nominate suits, as `computer_v1` would.
+pub fn suit_nominator() -> StrategyType
+{
+    computer_v1::ComputerStrategyV1::strategy_type("computer_v1")
+}
+
+impl CfrTrainer
+{
+    pub fn new(rules: Rules, seed: u64) -> CfrTrainer
+    {
+        CfrTrainer {
+            nodes: HashMap::new(),
+            rules,
+            players: vec![suit_nominator(); 2],
+            rng: StdRng::seed_from_u64(seed),
+            iterations: 0
+        }
+    }
+
+    /// Information sets reached so far.
+    pub fn size(&self) -> usize
+    {
+        self.nodes.len()
+    }
+
+    pub fn iterate(&mut self)
+    {
+        let traverser = self.iterations % 2;
+        let mut game = Game::with_seed(&self.players, self.rules.clone(), self.rng.gen());
+
+        // (information set, legal moves, strategy, move taken, chance of sampling it)
+        let mut visited = Vec::new();
+
+        while let Some(decision) = game.decision() {
+
+            if decision == Decision::Suit { game.decide(); continue; }
+
+            let seat = game.curr_player_id;
+            let hand = game.players[seat].hand;
+            let mut chains = move_chains(&game.table, &hand);
+            let legal = legal_moves(&chains);
+
+            // Keyed as `CfrStrategy` will see it, from the log alone.
+            let key = InfoSet::new(&game.table, &hand, opponent_cards(&hand, &game.log));
+            let node = self.nodes.entry(key).or_default();
+            let sigma = normalise(&node.regrets, &legal);
+
+            let m = if seat == traverser {
+                let count = legal.iter().filter(|l| **l).count() as f64;
+                let mut explore = [0.0; MOVES];
+
+                for m in (0..MOVES).filter(|m| legal[*m]) {
+                    explore[m] = EXPLORATION / count + (1.0 - EXPLORATION) * sigma[m];
+                }
+
+                let m = sample(&explore, &mut self.rng);
+                visited.push((key, legal, sigma, m, explore[m]));
+                m
+            } else {
+                for (avg, s) in node.average.iter_mut().zip(sigma.iter()) { *avg += s; }
+                sample(&sigma, &mut self.rng)
+            };
+
+            game.play(chains[m].take().unwrap());
+        }
+
+        let points = &game.result().unwrap().points;
+        let sign = if self.rules.scoring.lower_is_better() { 1.0 } else { -1.0 };
+        let utility = sign * (points[1 - traverser] - points[traverser]) as f64 / 100.0;
+
+        let sampled : f64 = visited.iter().map(|v| v.4).product();
+        let weight = utility / sampled;
+        let mut tail = 1.0;
+
+        for (key, legal, sigma, m, _) in visited.iter().rev() {
+            let node = self.nodes.get_mut(key).unwrap();
+
+            for a in (0..MOVES).filter(|a| legal[*a]) {
+                node.regrets[a] += if a == *m { weight * tail * (1.0 - sigma[a]) } else { -weight * tail * sigma[*m] };
+            }
+
+            tail *= sigma[*m];
+        }
+
+        self.iterations += 1;
+    }
+
+    pub fn policy(&self) -> Policy
+    {
+        let table = self.nodes
+                    .iter()
+                    .filter(|(_, node)| node.average.iter().sum::<f64>() > 0.0)
+                    .map(|(key, node)| {
+                        let total : f64 = node.average.iter().sum();
+                        (*key, node.average.map(|a| a / total))
+                    })
+                    .collect();
+
+        Policy { table }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    fn table(top: &str) -> TableState
+    {
+        let mut table = TableState::new(Arc::new(Rules::default()), card(top));
+        table.active = false;
+        table
+    }
+
+    #[test]
+    fn moves_stand_for_the_chains_they_describe()
+    {
+        let table = table("5S");
+        let hand : CardSet = ["5H", "6H", "2S", "AS", "9D"].iter().map(|c| card(c)).collect();
+
+        let chains = move_chains(&table, &hand);
+
+        assert!(chains[Move::PickUp.index()].is_none(), "nobody picks up while a card goes");
+
+        for chain in chains.iter().flatten() {
+            assert!(is_valid(&table, chain), "{:?} is not valid", chain);
+            assert!(chain.iter().all(|c| hand.contains(c)));
+        }
+
+        assert_eq!(chains[Move::Single.index()].as_ref().unwrap().len(), 1);
+        assert!(table.rules.penalty_value(*chains[Move::Attack.index()].as_ref().unwrap().last().unwrap()) > 0);
+        assert!(!chains[Move::Hold.index()].as_ref().unwrap().iter().any(|c| is_special(&table.rules, *c)));
+
+        let longest = chains[Move::Longest.index()].as_ref().unwrap().len();
+        assert!(chains.iter().flatten().all(|c| c.len() <= longest));
+    }
+
+    #[test]
+    fn picking_up_is_the_only_move_when_nothing_goes()
+    {
+        let table = table("5S");
+        let hand : CardSet = ["4H", "9D"].iter().map(|c| card(c)).collect();
+
+        let chains = move_chains(&table, &hand);
+
+        assert_eq!(legal_moves(&chains), [true, false, false, false, false, false]);
+        assert_eq!(chains[Move::PickUp.index()], Some(Vec::new()));
+    }
+
+    #[test]
+    fn buckets()
+    {
+        let buckets : Vec<u8> = (0..12).map(bucket).collect();
+        assert_eq!(buckets, vec![0, 1, 2, 3, 4, 4, 5, 5, 5, 6, 6, 6]);
+    }
+
+    #[test]
+    fn regrets_are_normalised_over_legal_moves()
+    {
+        let legal = [true, true, false, true, false, false];
+
+        assert_eq!(normalise(&[0.0, -1.0, 5.0, 0.0, 0.0, 0.0], &legal), [1.0 / 3.0, 1.0 / 3.0, 0.0, 1.0 / 3.0, 0.0, 0.0]);
+        assert_eq!(normalise(&[1.0, -1.0, 5.0, 3.0, 0.0, 0.0], &legal), [0.25, 0.0, 0.0, 0.75, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn opponent_cards_are_counted_from_the_log()
+    {
+        let log = vec![
+            Turn { player: None, action: Action::First(card("5S")) },
+            Turn { player: Some(0), action: Action::Played(vec![card("5H")]) },
+            Turn { player: Some(1), action: Action::PickedUp(2) }
+        ];
+
+        let hand : CardSet = (0..STARTING_CARD_COUNT - 1).map(Card::from_index).collect();
+
+        assert_eq!(opponent_cards(&hand, &log), STARTING_CARD_COUNT + 2);
+        assert_eq!(opponent_cards(&hand, &log[..1]), STARTING_CARD_COUNT);
+    }
+
+    #[test]
+    fn the_log_gives_the_true_opponent_hand_in_two_player_games()
+    {
+        let bots = vec![computer_v1::ComputerStrategyV1::strategy_type("computer_v1"); 2];
+
+        for seed in 0..50 {
+            let mut game = Game::with_seed(&bots, Rules::default(), seed);
+
+            while let Some(decision) = game.decision() {
+                let seat = game.curr_player_id;
+                let hand = game.players[seat].hand;
+
+                if decision == Decision::Chain {
+                    assert_eq!(opponent_cards(&hand, &game.log), game.players[1 - seat].hand.len(), "game {}", seed);
+                }
+
+                game.decide();
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_training_is_repeatable()
+    {
+        let train = || {
+            let mut trainer = CfrTrainer::new(Rules::default(), 7);
+            for _ in 0..300 { trainer.iterate(); }
+            trainer
+        };
+
+        let (a, b) = (train(), train());
+
+        assert_eq!(a.size(), b.size());
+        assert_eq!(a.policy().table, b.policy().table);
+
+        for p in a.policy().table.values() {
+            assert!((p.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn policies_survive_a_round_trip()
+    {
+        let mut trainer = CfrTrainer::new(Rules::default(), 11);
+        for _ in 0..300 { trainer.iterate(); }
+
+        let policy = trainer.policy();
+        let path = std::env::temp_dir().join(format!("cfr-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        policy.save(path).unwrap();
+        let loaded = Policy::load(path);
+        std::fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.table.len(), policy.table.len());
+
+        for (key, p) in &policy.table {
+            let q = loaded.table[key];
+            assert!(p.iter().zip(q.iter()).all(|(a, b)| (a - b).abs() < 1e-4));
+        }
+    }
+
+    #[test]
+    fn unseen_positions_play_the_greedy_chain()
+    {
+        let table = table("5S");
+        let hand : CardSet = ["5H", "6H", "2S", "9D"].iter().map(|c| card(c)).collect();
+
+        let mut strategy = CfrStrategy::new(Arc::new(Policy::default()));
+
+        assert_eq!(Some(strategy.choose_next(&hand, &table, &[])), move_chains(&table, &hand)[Move::Greedy.index()]);
+    }
+}
@@ -1,58 +1,34 @@
-use crate::blackjack::*;
+use crate::rules::*;
 use crate::cards::*;
 use crate::game::*;
 use crate::strategy::*;
 
-use std::collections::{HashSet,HashMap};
-
 pub struct ComputerStrategyV1 {}
 
+impl ComputerStrategyV1
+{
+    pub fn strategy_type(spec: &str) -> StrategyType
+    {
+        StrategyType::new(spec, |_| Box::new(ComputerStrategyV1 {}))
+    }
+}
+
 impl Strategy for ComputerStrategyV1
 {
-    fn choose_next(&mut self, hand: &HashSet<Card>, log: &[Turn]) -> Vec<Card>
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, _: &[Turn]) -> Vec<Card>
     {
-        let outstanding_penalty = outstanding_penalty(log);
-        
-        shared::find_best_valid(log, hand, |chain| {
-            score(outstanding_penalty, chain)
-        })
+        shared::find_best_valid(table, hand, |chain| shared::score(table, chain))
     }
     
-    fn choose_suit(&mut self, hand: &HashSet<Card>, _: &[Turn]) -> Suit
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
     {
-        choose_suit(hand)
+        shared::choose_suit(hand)
     }
 
     fn name(&self) -> &str { "Computer (v1)" }
-}
 
-fn choose_suit(hand: &HashSet<Card>) -> Suit
-{
-    let mut counts = HashMap::new();
-   
-    for card in hand {
-        *counts.entry(card.suit).or_insert(0) += 1;
+    fn score(&self, _: &CardSet, table: &TableState, chain: &[Card]) -> Option<f32>
+    {
+        Some(shared::score(table, chain))
     }
-
-    return *counts.iter()
-                .max_by(|a, b| a.1.cmp(&b.1))
-                .map(|(k, _v)| k).unwrap()
 }
-
-const PICK_UP_WEIGHTING : f32 = 10.0;
-
-fn score(outstanding_penalty: usize, chain: &[Card]) -> f32
-{
-    let mut score = chain.iter().map(card_score).sum::<i32>() as f32;
-
-    for i in (0..chain.len()).rev() {
-        let card = chain[i];
-        score += penalty_value(card) as f32 * PICK_UP_WEIGHTING;
-    }
-
-    if outstanding_penalty > 0 {
-        score += outstanding_penalty as f32 * PICK_UP_WEIGHTING;
-    }
-
-    return score;
-}
\ No newline at end of file
@@ -1,15 +1,14 @@
-use crate::blackjack::*;
+use crate::rules::*;
 use crate::cards::*;
 use crate::game::*;
 use crate::strategy::*;
 
-use std::collections::{HashSet,HashMap};
-use std::iter::FromIterator;
-
 pub struct ComputerStrategyV2 
 {
-    live_cards: HashSet<Card>,
-    dead_cards: HashSet<Card>,
+    players: usize,
+    live_cards: CardSet,
+    dead_cards: CardSet,
+    top_card: Option<Card>,
     log_processed: usize,
     deck_count: usize
 }
@@ -18,40 +17,65 @@ impl ComputerStrategyV2
 {
     pub fn new(players: &[StrategyType]) -> ComputerStrategyV2
     {
-        let deck_count = Card::full_deck().len() - players.len() * STARTING_CARD_COUNT;
-
         ComputerStrategyV2 
         {
-            live_cards: HashSet::from_iter(Card::full_deck()),
-            dead_cards: HashSet::new(),
+            players: players.len(),
+            live_cards: CardSet::new(),
+            dead_cards: CardSet::new(),
+            top_card: None,
             log_processed: 0,
-            deck_count
+            deck_count: 0
         }
     }
 
+    /// Starts tracking a new game, whose deck depends on the rules.
+    fn reset(&mut self, rules: &Rules)
+    {
+        self.live_cards = rules.deck(decks_for(self.players));
+        self.dead_cards.clear();
+        self.top_card = None;
+        self.log_processed = 0;
+        self.deck_count = self.live_cards.len() - self.players * STARTING_CARD_COUNT;
+    }
+
+    pub fn strategy_type(spec: &str) -> StrategyType
+    {
+        StrategyType::new(spec, |players| Box::new(ComputerStrategyV2::new(players)))
+    }
+
+    /// Marks one copy of `card` as played; with several decks the other
+    /// copies stay live.
+    fn play(&mut self, card: Card)
+    {
+        self.dead_cards.insert(card);
+        self.live_cards.remove(&card);
+        self.top_card = Some(card);
+    }
+
     pub fn process_new_events(&mut self, log: &[Turn])
     {
         for e in log[self.log_processed..log.len()].iter()
         {
             match &e.action {
                 Action::Played(chain) => {
-                    for c in chain {
-                        self.dead_cards.insert(*c);
-                        self.live_cards.remove(c);
-                    }
+                    for c in chain { self.play(*c); }
                 },
                 Action::PickedUp(n) => {
-                    if self.deck_count - n < 0
+                    if *n > self.deck_count
                     {
-                        let discard_pile_size = self.dead_cards.len();
-                        self.dead_cards.clear();
-                        self.live_cards.extend(Card::full_deck());
-                        self.deck_count += discard_pile_size - n;
+                        // Everything under the top card is shuffled back in.
+                        let mut reshuffled = self.dead_cards;
+                        if let Some(top) = self.top_card { reshuffled.remove(&top); }
+
+                        self.dead_cards = self.dead_cards - reshuffled;
+                        self.live_cards.extend(reshuffled.iter());
+                        self.deck_count += reshuffled.len();
                     }
+
+                    self.deck_count -= (*n).min(self.deck_count);
                 },
                 Action::First(c) => {
-                    self.dead_cards.insert(*c);
-                    self.live_cards.remove(c);
+                    self.play(*c);
                     self.deck_count -= 1;
                 },
                 | Action::Nominated(_) 
@@ -67,52 +91,24 @@ impl ComputerStrategyV2
 
 impl Strategy for ComputerStrategyV2
 {
-    fn choose_next(&mut self, hand: &HashSet<Card>, log: &[Turn]) -> Vec<Card>
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>
     {
+        if self.log_processed == 0 { self.reset(&table.rules); }
+
         self.process_new_events(log);
 
-        let outstanding_penalty = outstanding_penalty(log);
-        
-        shared::find_best_valid(log, hand, |chain| {
-            score(outstanding_penalty, chain)
-        })
+        shared::find_best_valid(table, hand, |chain| shared::score(table, chain))
     }
     
-    fn choose_suit(&mut self, hand: &HashSet<Card>, _: &[Turn]) -> Suit
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
     {
-        choose_suit(hand)
+        shared::choose_suit(hand)
     }
 
     fn name(&self) -> &str { "Computer (v2)" }
-}
 
-fn choose_suit(hand: &HashSet<Card>) -> Suit
-{
-    let mut counts = HashMap::new();
-   
-    for card in hand {
-        *counts.entry(card.suit).or_insert(0) += 1;
+    fn score(&self, _: &CardSet, table: &TableState, chain: &[Card]) -> Option<f32>
+    {
+        Some(shared::score(table, chain))
     }
-
-    return *counts.iter()
-                .max_by(|a, b| a.1.cmp(&b.1))
-                .map(|(k, _v)| k).unwrap()
 }
-
-const PICK_UP_WEIGHTING : f32 = 10.0;
-
-fn score(outstanding_penalty: usize, chain: &[Card]) -> f32
-{
-    let mut score = chain.iter().map(card_score).sum::<i32>() as f32;
-
-    for i in (0..chain.len()).rev() {
-        let card = chain[i];
-        score += penalty_value(card) as f32 * PICK_UP_WEIGHTING;
-    }
-
-    if outstanding_penalty > 0 {
-        score += outstanding_penalty as f32 * PICK_UP_WEIGHTING;
-    }
-
-    return score;
-}
\ No newline at end of file
@@ -0,0 +1,169 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+use crate::strategy::registry::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeuristicParams
+{
+    pub pick_up_weighting: f32,
+    pub dump_priority: [f32; 13],
+    pub defensive_retention: f32
+}
+
+impl Default for HeuristicParams
+{
+    fn default() -> HeuristicParams
+    {
+        HeuristicParams
+        {
+            pick_up_weighting: shared::PICK_UP_WEIGHTING,
+            dump_priority: [1.0; 13],
+            defensive_retention: 0.0
+        }
+    }
+}
+
+const DUMP_PARAMETERS : [&str; 13] = [
+    "dump_2", "dump_3", "dump_4", "dump_5", "dump_6", "dump_7", "dump_8",
+    "dump_9", "dump_10", "dump_j", "dump_q", "dump_k", "dump_a"
+];
+
+impl HeuristicParams
+{
+    pub fn parameter_names() -> Vec<&'static str>
+    {
+        let mut names = vec!["pickup_weight", "defensive_retention"];
+        names.extend_from_slice(&DUMP_PARAMETERS);
+        names
+    }
+
+    pub fn from_params(params: &Params) -> Result<HeuristicParams, String>
+    {
+        let default = HeuristicParams::default();
+        let mut dump_priority = default.dump_priority;
+
+        for (w, name) in dump_priority.iter_mut().zip(DUMP_PARAMETERS.iter()) {
+            *w = params.get(name, *w)?;
+        }
+
+        Ok(HeuristicParams
+        {
+            pick_up_weighting: params.get("pickup_weight", default.pick_up_weighting)?,
+            dump_priority,
+            defensive_retention: params.get("defensive_retention", default.defensive_retention)?
+        })
+    }
+
+    /// The parameters of a `heuristic:...` spec, as written by `to_spec`.
+    pub fn from_spec(spec: &str) -> Result<HeuristicParams, String>
+    {
+        match spec.split_once(':') {
+            Some(("heuristic", params)) => HeuristicParams::from_params(&Params::parse(params)?),
+            None if spec == "heuristic" => Ok(HeuristicParams::default()),
+            _ => Err(format!("'{}' is not a heuristic spec", spec))
+        }
+    }
+
+    /// A spec that `Registry::parse` turns back into these parameters.
+    pub fn to_spec(&self) -> String
+    {
+        let mut params = vec![
+            format!("pickup_weight={}", self.pick_up_weighting),
+            format!("defensive_retention={}", self.defensive_retention)
+        ];
+
+        for (w, name) in self.dump_priority.iter().zip(DUMP_PARAMETERS.iter()) {
+            params.push(format!("{}={}", name, w));
+        }
+
+        format!("heuristic:{}", params.join(","))
+    }
+
+    pub fn score(&self, table: &TableState, chain: &[Card]) -> f32
+    {
+        let mut score = 0.0;
+
+        for card in chain {
+            let priority = if card.is_joker() { 1.0 } else { self.dump_priority[card.rank().index()] };
+
+            score += table.rules.card_score(*card) as f32 * priority;
+            score += table.rules.penalty_value(*card) as f32 * self.pick_up_weighting;
+
+            if table.penalty == 0 && shared::is_defensive(&table.rules, *card) {
+                score -= self.defensive_retention;
+            }
+        }
+
+        if table.penalty > 0 {
+            score += table.penalty as f32 * self.pick_up_weighting;
+        }
+
+        score
+    }
+}
+
+pub struct HeuristicStrategy
+{
+    params: HeuristicParams
+}
+
+impl HeuristicStrategy
+{
+    pub fn new(params: HeuristicParams) -> HeuristicStrategy
+    {
+        HeuristicStrategy { params }
+    }
+
+    pub fn strategy_type(spec: &str, params: HeuristicParams) -> StrategyType
+    {
+        StrategyType::new(spec, move |_| Box::new(HeuristicStrategy::new(params)))
+    }
+}
+
+impl Strategy for HeuristicStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, _: &[Turn]) -> Vec<Card>
+    {
+        let params = &self.params;
+
+        shared::find_best_valid(table, hand, |chain| params.score(table, chain))
+    }
+
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
+    {
+        shared::choose_suit(hand)
+    }
+
+    fn name(&self) -> &str { "Heuristic" }
+
+    fn score(&self, _: &CardSet, table: &TableState, chain: &[Card]) -> Option<f32>
+    {
+        Some(self.params.score(table, chain))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn specs_read_back_the_parameters_they_were_written_from()
+    {
+        let mut params = HeuristicParams { pick_up_weighting: 1.25, defensive_retention: 7.5, ..HeuristicParams::default() };
+        params.dump_priority[12] = 0.125;
+        params.dump_priority[0] = 3.0;
+
+        for p in [HeuristicParams::default(), params] {
+            assert_eq!(HeuristicParams::from_spec(&p.to_spec()), Ok(p));
+            assert_eq!(Registry::standard().parse(&p.to_spec()).unwrap().spec, p.to_spec());
+        }
+
+        assert_eq!(HeuristicParams::from_spec("heuristic"), Ok(HeuristicParams::default()));
+        assert_eq!(HeuristicParams::from_spec("heuristic:dump_a=2").unwrap().dump_priority[12], 2.0);
+        assert!(HeuristicParams::from_spec("computer_v1").is_err());
+        assert!(HeuristicParams::from_spec("heuristic:dump_a=x").is_err());
+    }
+}
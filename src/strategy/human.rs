@@ -1,143 +1,436 @@
-use crate::blackjack::*;
+use crate::rules::*;
 use crate::cards::*;
 use crate::game::*;
 use crate::strategy::*;
+use crate::strategy::advisor::*;
 
-use std::collections::HashSet;
+use std::convert::TryFrom;
 
-pub struct HumanStrategy {}
+/// Chains listed by the `hint` command.
+const HINTS : usize = 5;
 
-fn parse_chain(s: &str) -> Option<Vec<Card>>
+pub struct HumanStrategy
 {
-    let mut chain = Vec::new();
-
-    if s.trim().is_empty() { return Some(chain); }
-
-    for ss in s.trim().split(",") {
-        
-        if ss.len() != 2 { return None; }
-        
-        let rank;
-        let suit;
-        
-        match ss.chars().nth(0) {
-            Some(rc) => match rc {
-                'a' | 'A' => { rank = Rank::Ace; },
-                'k' | 'K' => { rank = Rank::King; },
-                'q' | 'Q' => { rank = Rank::Queen; },
-                'j' | 'J' => { rank = Rank::Jack; },
-                'x' | 'X' => { rank = Rank::Val(10); },
-                s => match s.to_digit(10) {
-                    Some(i) => match i {
-                        1 => { return None; },
-                        _ => { rank = Rank::Val(i as i32); } 
-                    },
-                    _ => { return None; }
-                }
-            },
-            None => { return None; }
-        }
+    advisor: Advisor
+}
 
-        match ss.chars().nth(1) {
-            Some(sc) => match parse_suit(sc) {
-                    Some(s) => { suit = s; },
-                    None => { return None; }
-            },
-            None => { return None; }
+impl HumanStrategy
+{
+    /// `advisor` is the bot whose ranking `hint` shows, and which plays on
+    /// once input runs out.
+    pub fn strategy_type(spec: &str, advisor: StrategyType) -> Result<StrategyType, String>
+    {
+        if advisor.spec.split(':').next() == Some("human") {
+            return Err("A human can't be the advisor".to_string());
         }
 
-        chain.push(Card { rank, suit });        
+        Ok(StrategyType::new(spec, move |players| Box::new(HumanStrategy { advisor: Advisor::new(&advisor, players) })))
+    }
+}
+
+const RANK_NAMES : [&str; 13] = ["two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "jack", "queen", "king", "ace"];
+
+/// A rank as a code (`X`, `T`, `q`), a number from 2 to 10 or a word.
+fn rank_named(s: &str) -> Option<Rank>
+{
+    if let Ok(n) = s.parse::<u8>() {
+        return if n <= 10 { Rank::try_from(n).ok() } else { None };
     }
 
-    Some(chain)
+    if let Some(i) = RANK_NAMES.iter().position(|name| *name == s) { return Some(Rank::all()[i]); }
+
+    let mut chars = s.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Rank::try_from(c.to_ascii_uppercase()).ok(),
+        _ => None
+    }
 }
 
-fn hand_contains_chain(hand: &HashSet<Card>, chain: &[Card]) -> bool
+/// A suit as a code, a symbol or a word, e.g. `h`, `♥` or `hearts`.
+pub fn suit_named(s: &str) -> Option<Suit>
 {
-    for card in chain { if !hand.contains(card) { return false; } }
-    return true;
+    let s = s.trim().to_lowercase();
+
+    let word = match s.as_str() {
+        "spade" | "spades" => Some(Suit::Spades),
+        "club" | "clubs" => Some(Suit::Clubs),
+        "heart" | "hearts" => Some(Suit::Hearts),
+        "diamond" | "diamonds" => Some(Suit::Diamonds),
+        _ => None
+    };
+
+    let mut chars = s.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => parse_suit(c.to_ascii_uppercase()),
+        _ => word
+    }
 }
 
-fn parse_suit(c: char) -> Option<Suit>
+/// A card written loosely: `XD`, `10d`, `10♦`, `ten of diamonds`, `JR` or
+/// `red joker`.
+pub fn parse_card(s: &str) -> Option<Card>
 {
-    match c {
-        's' | 'S' => Some(Suit::Spades),
-        'h' | 'H' => Some(Suit::Hearts),
-        'd' | 'D' => Some(Suit::Diamonds),
-        'c' | 'C' => Some(Suit::Clubs),
+    let s = s.trim().to_lowercase();
+    let words : Vec<&str> = s.split_whitespace().filter(|w| *w != "of").collect();
+
+    let joker = |colour: &str| match colour {
+        "r" | "red" => Some(Card::joker(Colour::Red)),
+        "b" | "black" => Some(Card::joker(Colour::Black)),
         _ => None
+    };
+
+    match words[..] {
+        ["joker", colour] | [colour, "joker"] => joker(colour),
+        [rank, suit] => Some(Card::new(rank_named(rank)?, suit_named(suit)?)),
+        ["jr"] | ["jb"] => joker(&s[1..]),
+        [code] => {
+            let (split, _) = code.char_indices().last()?;
+            Some(Card::new(rank_named(&code[..split])?, suit_named(&code[split..])?))
+        },
+        _ => None
+    }
+}
+
+/// Comma separated cards, e.g. `"9D,10D,JR"`; blank for no cards.
+pub fn parse_chain(s: &str) -> Option<Vec<Card>>
+{
+    if s.trim().is_empty() { return Some(Vec::new()); }
+
+    s.trim()
+        .split(',')
+        .map(parse_card)
+        .collect()
+}
+
+pub fn parse_suit(c: char) -> Option<Suit>
+{
+    Suit::try_from(c).ok()
+}
+
+/// Cards picked by their 1-based index in `shown` or by name, separated by
+/// commas, or by spaces where that isn't ambiguous.
+pub fn parse_selection(s: &str, shown: &[Card]) -> Result<Vec<Card>, String>
+{
+    let item = |word: &str| match word.parse::<usize>() {
+        Ok(i) if i >= 1 && i <= shown.len() => Ok(shown[i - 1]),
+        Ok(i) => Err(format!("There is no card {}; pick from 1 to {}.", i, shown.len())),
+        Err(_) => parse_card(word).ok_or(format!("'{}' isn't a card.", word))
+    };
+
+    let mut cards = Vec::new();
+
+    for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match parse_card(part) {
+            Some(card) => cards.push(card),
+            None => for word in part.split_whitespace() { cards.push(item(word)?); }
+        }
+    }
+
+    Ok(cards)
+}
+
+/// The one chain starting with `first` that every other chain starting with
+/// it leads into, if it is longer than `first` alone. Nothing is completed
+/// when the search was cut short, as a chain it missed may branch off.
+fn complete(table: &TableState, hand: &CardSet, first: Card) -> Option<Vec<Card>>
+{
+    let mut chains : Vec<Vec<Card>> = Vec::new();
+
+    let searched = shared::iterate_valid_chains(table, hand, &mut |chain: &[Card]| {
+        if chain[0] == first { chains.push(chain.to_vec()); }
+    });
+
+    if searched == shared::Enumeration::Truncated { return None; }
+
+    let longest = chains.iter().max_by_key(|c| c.len())?;
+
+    if longest.len() > 1 && chains.iter().all(|c| longest.starts_with(c)) { Some(longest.clone()) } else { None }
+}
+
+/// The chain being built, a line of input at a time.
+#[derive(Default)]
+struct Selection
+{
+    chain: Vec<Card>,
+    // How many cards each line added, so undo takes back a whole line,
+    // completion included.
+    added: Vec<usize>
+}
+
+impl Selection
+{
+    /// Adds `cards` to the chain, completing it when they start it and only
+    /// one chain follows, and returns whether it was completed. Cards that
+    /// can't be added are refused with the reason.
+    fn add(&mut self, table: &TableState, hand: &CardSet, cards: &[Card]) -> Result<bool, String>
+    {
+        let mut next = self.chain.clone();
+        next.extend(cards);
+
+        if let Some(why) = explain(table, hand, &next) { return Err(why); }
+
+        let completed = if next.len() == 1 { complete(table, hand, next[0]) } else { None };
+        let was_completed = completed.is_some();
+        let next = completed.unwrap_or(next);
+
+        self.added.push(next.len() - self.chain.len());
+        self.chain = next;
+        Ok(was_completed)
+    }
+
+    /// Takes back the last line added, returning false if there was none.
+    fn undo(&mut self) -> bool
+    {
+        match self.added.pop() {
+            Some(n) => { self.chain.truncate(self.chain.len() - n); true },
+            None => false
+        }
+    }
+}
+
+/// The hand numbered for selection, with a `*` by each card already chosen.
+pub fn show_hand(shown: &[Card], chosen: &[Card]) -> String
+{
+    let mut chosen : CardSet = chosen.iter().copied().collect();
+
+    shown.iter()
+         .enumerate()
+         .map(|(i, card)| format!("{:>3}{}{:?}", i + 1, if chosen.remove(card) { '*' } else { ' ' }, card))
+         .collect::<Vec<String>>()
+         .join(" ")
+}
+
+fn read_input() -> Option<String>
+{
+    let mut input = String::new();
+
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input.trim().to_string())
     }
 }
 
 impl Strategy for HumanStrategy
 {
-    fn choose_next(&mut self, hand: &HashSet<Card>, log: &[Turn]) -> Vec<Card>
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, log: &[Turn]) -> Vec<Card>
     {
+        let shown : Vec<Card> = hand.iter().collect();
+        let mut selection = Selection::default();
+
         loop {
-            
-            println!("Current hand: {:?}", hand);
-            println!("Choose cards to put down:");
-            
-            let mut input = String::new();
-            
-            match std::io::stdin().read_line(&mut input) {
-                Ok(_) => {
-                    match parse_chain(&input) {
-                        Some(chain) => {
-                            if !hand_contains_chain(hand, &chain) {
-                                println!("Cannot make chain from hand.");
-                                continue;
-                            }
-                            if chain.len() > 0 {
-                                if !is_valid(log, &chain) {
-                                    println!("Chain is invalid.");
-                                    continue;
-                                }
-                            } else {
-                                if can_go(log, hand) {
-                                    println!("Valid cards in deck - you must go.");
-                                    continue;
-                                }
-                            }
-                            return chain 
-                        }, 
-                        _ => {}
+
+            println!("Current hand: {}", show_hand(&shown, &selection.chain));
+
+            if selection.chain.is_empty() {
+                println!("Choose cards to put down by number or name, 'hint', or nothing to pick up:");
+            } else {
+                println!("Chain: {:?}. Add cards, 'undo' the last ones, or press Enter to play it:", selection.chain);
+            }
+
+            let input = match read_input() {
+                Some(input) => input,
+                None => {
+                    let chain = self.advisor.play(hand, table, log);
+                    println!("No more input; playing {:?}.", chain);
+                    return chain;
+                }
+            };
+
+            match input.to_lowercase().as_str() {
+                "hint" => print!("{}", self.advisor.hint(hand, table, log, HINTS)),
+                "undo" | "u" => if !selection.undo() { println!("Nothing to undo.") },
+                "" => match explain(table, hand, &selection.chain) {
+                    None => return selection.chain,
+                    Some(why) => {
+                        let going : Vec<String> = shown.iter()
+                                                        .enumerate()
+                                                        .filter(|(_, card)| can_follow(table, **card))
+                                                        .map(|(i, card)| format!("{} {:?}", i + 1, card))
+                                                        .collect();
+                        println!("{} These go: {}", why, going.join(", "));
+                    }
+                },
+                _ => match parse_selection(&input, &shown) {
+                    Err(e) => println!("{} Type numbers or cards like 9D, 10h or ten of hearts.", e),
+                    Ok(cards) => match selection.add(table, hand, &cards) {
+                        Ok(true) => println!("Completed to {:?}; 'undo' takes it back.", selection.chain),
+                        Ok(false) => (),
+                        Err(why) => println!("{}", why)
                     }
-                }, 
-                Err(_) => {
-                    println!("Invalid input.");
-                    continue;
                 }
             }
-        }   
+        }
     }
 
-    fn choose_suit(&mut self, hand: &HashSet<Card>, _: &[Turn]) -> Suit
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
     {
         loop {
-            
+
             println!("Current hand: {:?}", hand);
             println!("Choose suit:");
-            
-            let mut input = String::new();
-            
-            match std::io::stdin().read_line(&mut input) {
-                
-                Ok(_) => {
-
-                    if let Some(c) = input.chars().nth(0) {
-                        if let Some(suit) = parse_suit(c) {
-                            return suit;
-                        }
-                    }
-                    
-                    println!("Invalid input.");
-                    continue;
-                }, 
-                Err(_) => {}
+
+            match read_input() {
+                Some(input) => match suit_named(&input) {
+                    Some(suit) => return suit,
+                    None => println!("Type a suit like S, ♥ or clubs.")
+                },
+                None => {
+                    let suit = shared::choose_suit(hand);
+                    println!("No more input; choosing {:?}.", suit);
+                    return suit;
+                }
             }
         }
     }
 
     fn name(&self) -> &str { "Human" }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::sync::Arc;
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    fn cards(codes: &[&str]) -> Vec<Card>
+    {
+        codes.iter().map(|c| card(c)).collect()
+    }
+
+    fn table(top: &str) -> TableState
+    {
+        let mut table = TableState::new(Arc::new(Rules::default()), card(top));
+        table.active = false;
+        table
+    }
+
+    #[test]
+    fn cards_written_loosely()
+    {
+        let ten_of_diamonds = Some(Card::new(Rank::Ten, Suit::Diamonds));
+
+        for input in ["XD", "xd", "10d", "10D", "10♦", "td", "ten of diamonds", "Ten Diamonds", " 10 of ♦ "] {
+            assert_eq!(parse_card(input), ten_of_diamonds, "{}", input);
+        }
+
+        let red = Some(Card::joker(Colour::Red));
+        let black = Some(Card::joker(Colour::Black));
+
+        for (input, expected) in [("JR", red), ("jr", red), ("red joker", red), ("joker red", red), ("JB", black), ("black joker", black)] {
+            assert_eq!(parse_card(input), expected, "{}", input);
+        }
+
+        assert_eq!(parse_card("queen of spades"), Some(Card::new(Rank::Queen, Suit::Spades)));
+        assert_eq!(parse_card("2♣"), Some(Card::new(Rank::Two, Suit::Clubs)));
+        assert_eq!(parse_card("AH"), Some(Card::new(Rank::Ace, Suit::Hearts)));
+
+        for input in ["", "11d", "1d", "zz", "ten of", "green joker", "10", "ten of diamonds please"] {
+            assert_eq!(parse_card(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn ranks_and_suits_written_loosely()
+    {
+        assert_eq!(rank_named("x"), Some(Rank::Ten));
+        assert_eq!(rank_named("10"), Some(Rank::Ten));
+        assert_eq!(rank_named("7"), Some(Rank::Seven));
+        assert_eq!(rank_named("king"), Some(Rank::King));
+        assert_eq!(rank_named("q"), Some(Rank::Queen));
+        assert_eq!(rank_named("11"), None);
+        assert_eq!(rank_named("kings"), None);
+
+        for (input, expected) in [("h", Some(Suit::Hearts)), ("♥", Some(Suit::Hearts)), ("Hearts", Some(Suit::Hearts)),
+                                  ("spade", Some(Suit::Spades)), ("C", Some(Suit::Clubs)), ("♦", Some(Suit::Diamonds)),
+                                  ("x", None), ("", None), ("stars", None)] {
+            assert_eq!(suit_named(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn selections_by_number_or_name()
+    {
+        let shown = cards(&["5H", "9S", "XD"]);
+
+        for (input, expected) in [
+            ("1", vec!["5H"]),
+            ("1 3", vec!["5H", "XD"]),
+            ("3,1", vec!["XD", "5H"]),
+            ("1, 9s", vec!["5H", "9S"]),
+            ("ten of diamonds", vec!["XD"]),
+            ("2, ten of diamonds", vec!["9S", "XD"]),
+            ("", vec![])
+        ] {
+            assert_eq!(parse_selection(input, &shown), Ok(cards(&expected)), "{}", input);
+        }
+
+        assert_eq!(parse_selection("4", &shown), Err("There is no card 4; pick from 1 to 3.".to_string()));
+        assert_eq!(parse_selection("0", &shown), Err("There is no card 0; pick from 1 to 3.".to_string()));
+        assert_eq!(parse_selection("1 zz", &shown), Err("'zz' isn't a card.".to_string()));
+    }
+
+    #[test]
+    fn completion_only_when_one_chain_follows()
+    {
+        let table = table("5S");
+        let hand = |codes: &[&str]| -> CardSet { cards(codes).into_iter().collect() };
+
+        assert_eq!(complete(&table, &hand(&["5H", "6H", "9D"]), card("5H")), Some(cards(&["5H", "6H"])));
+
+        // 5H can go on to 6H or 4H.
+        assert_eq!(complete(&table, &hand(&["5H", "6H", "4H"]), card("5H")), None);
+
+        // Nothing follows 9S.
+        assert_eq!(complete(&table, &hand(&["9S", "4D"]), card("9S")), None);
+    }
+
+    #[test]
+    fn undo_takes_back_a_whole_line()
+    {
+        let table = table("5S");
+
+        let completing : CardSet = cards(&["5H", "6H", "9D"]).into_iter().collect();
+        let mut selection = Selection::default();
+
+        assert_eq!(selection.add(&table, &completing, &cards(&["5H"])), Ok(true));
+        assert_eq!(selection.chain, cards(&["5H", "6H"]));
+        assert!(selection.undo());
+        assert!(selection.chain.is_empty());
+        assert!(!selection.undo());
+
+        let branching : CardSet = cards(&["5H", "6H", "4H", "9D"]).into_iter().collect();
+        let mut selection = Selection::default();
+
+        assert_eq!(selection.add(&table, &branching, &cards(&["5H"])), Ok(false));
+        assert_eq!(selection.add(&table, &branching, &cards(&["6H"])), Ok(false));
+        assert!(selection.add(&table, &branching, &cards(&["9D"])).is_err());
+        assert_eq!(selection.chain, cards(&["5H", "6H"]));
+
+        assert!(selection.undo());
+        assert_eq!(selection.chain, cards(&["5H"]));
+        assert!(selection.undo());
+        assert!(!selection.undo());
+    }
+
+    #[test]
+    fn any_bot_can_advise_but_a_human()
+    {
+        let registry = crate::strategy::registry::Registry::standard();
+
+        for spec in ["human", "human:advisor=random", "human:advisor=heuristic:pickup_weight=3", "human:advisor=computer_v2"] {
+            assert!(registry.parse(spec).is_ok(), "{}", spec);
+        }
+
+        for spec in ["human:advisor=nobody", "human:advisor=human", "human:advisor=human:advisor=random"] {
+            assert!(registry.parse(spec).is_err(), "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::rules::*;
+use crate::cards::*;
+use crate::game::*;
+use crate::strategy::*;
+
+use std::sync::Arc;
+
+/// Network inputs, in order: hand left after playing the chain, cards in the
+/// chain, last card of the chain and the current top card (`CARD_TYPES` slots
+/// each, indexed by `Card::index` and counting copies when playing with more
+/// than one deck), the nominated suit (4 slots, indexed by
+/// `Suit::index`), then the outstanding penalty and chain length, both / 10.
+pub const INPUT_SIZE : usize = CARD_TYPES * 4 + 4 + 2;
+
+/// The inputs of networks from before jokers: the same layout with 52 slots
+/// per group of cards. Such networks still load, and don't see jokers.
+pub const STANDARD_INPUT_SIZE : usize = 52 * 4 + 4 + 2;
+
+/// Limits on what a network file can ask to be allocated.
+const MAX_LAYERS : usize = 64;
+const MAX_LAYER_WEIGHTS : usize = 1 << 24;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activation
+{
+    Linear,
+    Relu,
+    Tanh
+}
+
+impl Activation {
+
+    fn parse(s: &str) -> Option<Activation>
+    {
+        match s {
+            "linear" => Some(Activation::Linear),
+            "relu" => Some(Activation::Relu),
+            "tanh" => Some(Activation::Tanh),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &str
+    {
+        match self {
+            Activation::Linear => "linear",
+            Activation::Relu => "relu",
+            Activation::Tanh => "tanh"
+        }
+    }
+
+    fn apply(&self, x: f32) -> f32
+    {
+        match self {
+            Activation::Linear => x,
+            Activation::Relu => x.max(0.0),
+            Activation::Tanh => x.tanh()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Layer
+{
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>,
+    pub activation: Activation
+}
+
+impl Layer {
+
+    fn forward(&self, input: &[f32]) -> Vec<f32>
+    {
+        (0..self.outputs).map(|o| {
+            let row = &self.weights[o * self.inputs..(o + 1) * self.inputs];
+            let sum = row.iter().zip(input).map(|(w, x)| w * x).sum::<f32>();
+            self.activation.apply(sum + self.biases[o])
+        }).collect()
+    }
+}
+
+/// A small feed-forward network mapping an encoded (state, chain) pair to a
+/// single score. It takes either `INPUT_SIZE` or `STANDARD_INPUT_SIZE`
+/// inputs, as its first layer says.
+///
+/// The file format is whitespace separated text so it can be written from
+/// numpy without extra dependencies; lines starting with `#` are ignored.
+///
+/// ```text
+/// layers <count>
+/// dense <inputs> <outputs> <linear|relu|tanh>
+/// <outputs x inputs weights, row-major>
+/// <outputs biases>
+/// ...
+/// ```
+#[derive(Clone, Debug)]
+pub struct Network
+{
+    pub layers: Vec<Layer>
+}
+
+impl Network {
+
+    pub fn load(path: &str) -> Result<Network, String>
+    {
+        let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {}: {}", path, e))?;
+
+        Network::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Network, String>
+    {
+        let mut tokens = text
+                        .lines()
+                        .filter(|l| !l.trim_start().starts_with('#'))
+                        .flat_map(|l| l.split_whitespace());
+
+        let mut next = |what: &str| tokens.next().ok_or(format!("Expected {}", what));
+
+        fn number<T: std::str::FromStr>(s: &str) -> Result<T, String>
+        {
+            s.parse::<T>().map_err(|_| format!("Invalid number '{}'", s))
+        }
+
+        if next("header")? != "layers" { return Err("Expected 'layers'".to_string()); }
+
+        let count : usize = number(next("layer count")?)?;
+        if count > MAX_LAYERS { return Err(format!("{} layers is more than {} max", count, MAX_LAYERS)); }
+
+        let mut layers = Vec::with_capacity(count);
+
+        for _ in 0..count {
+
+            if next("layer")? != "dense" { return Err("Expected 'dense'".to_string()); }
+
+            let inputs : usize = number(next("input size")?)?;
+            let outputs : usize = number(next("output size")?)?;
+
+            let activation_name = next("activation")?;
+            let activation = Activation::parse(activation_name)
+                            .ok_or(format!("Unknown activation '{}'", activation_name))?;
+
+            let size = inputs.checked_mul(outputs).filter(|n| (1..=MAX_LAYER_WEIGHTS).contains(n))
+                        .ok_or(format!("A {}x{} layer is more than {} weights max", inputs, outputs, MAX_LAYER_WEIGHTS))?;
+
+            let mut weights = Vec::with_capacity(size);
+            for _ in 0..size { weights.push(number(next("weight")?)?); }
+
+            let mut biases = Vec::with_capacity(outputs);
+            for _ in 0..outputs { biases.push(number(next("bias")?)?); }
+
+            layers.push(Layer { inputs, outputs, weights, biases, activation });
+        }
+
+        let network = Network { layers };
+        network.validate()?;
+        Ok(network)
+    }
+
+    fn validate(&self) -> Result<(), String>
+    {
+        let mut expected = match self.inputs() {
+            INPUT_SIZE | STANDARD_INPUT_SIZE => self.inputs(),
+            0 => return Err("Network has no layers".to_string()),
+            n => return Err(format!("Network takes {} inputs, not {} or {} without jokers", n, INPUT_SIZE, STANDARD_INPUT_SIZE))
+        };
+
+        for layer in &self.layers {
+            if layer.inputs != expected {
+                return Err(format!("Layer expects {} inputs but receives {}", layer.inputs, expected));
+            }
+            expected = layer.outputs;
+        }
+
+        if expected != 1 { return Err("Network must have a single output".to_string()); }
+
+        Ok(())
+    }
+
+    /// The size of the input the first layer takes.
+    pub fn inputs(&self) -> usize
+    {
+        self.layers.first().map_or(0, |l| l.inputs)
+    }
+
+    pub fn evaluate(&self, input: &[f32]) -> f32
+    {
+        let mut x = input.to_vec();
+        for layer in &self.layers { x = layer.forward(&x); }
+        x[0]
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "layers {}", self.layers.len())?;
+
+        for layer in &self.layers {
+            writeln!(f, "dense {} {} {}", layer.inputs, layer.outputs, layer.activation.name())?;
+
+            for row in layer.weights.chunks(layer.inputs) {
+                let row : Vec<String> = row.iter().map(|w| w.to_string()).collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
+
+            let biases : Vec<String> = layer.biases.iter().map(|b| b.to_string()).collect();
+            writeln!(f, "{}", biases.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fills `input`, which is `INPUT_SIZE` long or `STANDARD_INPUT_SIZE` long
+/// to leave jokers out.
+pub fn encode(hand: &CardSet, table: &TableState, chain: &[Card], input: &mut [f32])
+{
+    for x in input.iter_mut() { *x = 0.0; }
+
+    let played : CardSet = chain.iter().copied().collect();
+
+    let n = if input.len() == STANDARD_INPUT_SIZE { 52 } else { CARD_TYPES };
+    let mut set = |group: usize, card: &Card, value: f32| {
+        if card.index() < n { input[group * n + card.index()] += value; }
+    };
+
+    for card in &(*hand - played) { set(0, &card, 1.0); }
+
+    for card in chain { set(1, card, 1.0); }
+
+    if let Some(last) = chain.last() { set(2, last, 1.0); }
+
+    set(3, &table.top_card, 1.0);
+
+    if let Some(suit) = table.nominated { input[4 * n + suit.index()] = 1.0; }
+
+    input[4 * n + 4] = table.penalty as f32 / 10.0;
+    input[4 * n + 5] = chain.len() as f32 / 10.0;
+}
+
+pub struct NeuralStrategy
+{
+    network: Arc<Network>
+}
+
+impl NeuralStrategy
+{
+    pub fn new(network: Arc<Network>) -> NeuralStrategy
+    {
+        NeuralStrategy { network }
+    }
+
+    pub fn strategy_type(spec: &str, path: &str) -> Result<StrategyType, String>
+    {
+        let network = Arc::new(Network::load(path)?);
+
+        Ok(StrategyType::new(spec, move |_| Box::new(NeuralStrategy::new(network.clone()))))
+    }
+}
+
+impl Strategy for NeuralStrategy
+{
+    fn choose_next(&mut self, hand: &CardSet, table: &TableState, _: &[Turn]) -> Vec<Card>
+    {
+        let network = &self.network;
+        let mut input = vec![0.0; network.inputs()];
+
+        shared::find_best_valid(table, hand, |chain| {
+            encode(hand, table, chain, &mut input);
+            network.evaluate(&input)
+        })
+    }
+
+    fn choose_suit(&mut self, hand: &CardSet, _: &[Turn]) -> Suit
+    {
+        shared::choose_suit(hand)
+    }
+
+    fn name(&self) -> &str { "Neural" }
+
+    fn score(&self, hand: &CardSet, table: &TableState, chain: &[Card]) -> Option<f32>
+    {
+        let mut input = vec![0.0; self.network.inputs()];
+        encode(hand, table, chain, &mut input);
+        Some(self.network.evaluate(&input))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    /// A single linear layer weighting every input by `weight`.
+    fn linear(inputs: usize, weight: f32) -> String
+    {
+        format!("layers 1\ndense {} 1 linear\n{}\n0\n", inputs, vec![weight.to_string(); inputs].join(" "))
+    }
+
+    fn card(code: &str) -> Card
+    {
+        Card::try_from(code).unwrap()
+    }
+
+    #[test]
+    fn loads_either_layout()
+    {
+        assert_eq!(Network::parse(&linear(INPUT_SIZE, 1.0)).unwrap().inputs(), INPUT_SIZE);
+        assert_eq!(Network::parse(&linear(STANDARD_INPUT_SIZE, 1.0)).unwrap().inputs(), STANDARD_INPUT_SIZE);
+        assert!(Network::parse(&linear(INPUT_SIZE - 1, 1.0)).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_text()
+    {
+        let network = Network::parse(&linear(STANDARD_INPUT_SIZE, 0.5)).unwrap();
+        let reparsed = Network::parse(&network.to_string()).unwrap();
+
+        assert_eq!(reparsed.layers[0].weights, network.layers[0].weights);
+    }
+
+    #[test]
+    fn standard_layout_leaves_jokers_out()
+    {
+        let mut rules = Rules::default();
+        rules.jokers_per_deck = 2;
+
+        let table = TableState::new(Arc::new(rules), card("2H"));
+        let hand : CardSet = [card("JR"), card("5S"), card("5C")].iter().copied().collect();
+        let chain = [card("5C")];
+
+        let mut full = vec![0.0; INPUT_SIZE];
+        let mut standard = vec![0.0; STANDARD_INPUT_SIZE];
+
+        encode(&hand, &table, &chain, &mut full);
+        encode(&hand, &table, &chain, &mut standard);
+
+        assert_eq!(full.iter().take(CARD_TYPES).sum::<f32>(), 2.0);
+        assert_eq!(standard.iter().take(52).sum::<f32>(), 1.0);
+        assert_eq!(standard[52 + card("5C").index()], 1.0);
+        assert_eq!(standard[3 * 52 + card("2H").index()], 1.0);
+        assert_eq!(standard[4 * 52 + 4], 0.2);
+        assert_eq!(standard[4 * 52 + 5], 0.1);
+        assert_eq!(&full[4 * CARD_TYPES..], &standard[4 * 52..]);
+    }
+
+    #[test]
+    fn sizes_a_file_asks_for_are_checked_before_allocating()
+    {
+        let huge = usize::MAX.to_string();
+
+        for text in [format!("layers {}\n", huge),
+                     format!("layers 1\ndense {} 2 linear\n", huge),
+                     format!("layers 1\ndense 0 {} linear\n", huge),
+                     "layers 1\ndense 100000 100000 linear\n".to_string()] {
+            assert!(Network::parse(&text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn a_network_answering_nan_still_plays()
+    {
+        let network = Network::parse(&linear(INPUT_SIZE, f32::NAN)).unwrap();
+        let mut strategy = NeuralStrategy::new(Arc::new(network));
+
+        let table = TableState::new(Arc::new(Rules::default()), card("5S"));
+        let hand : CardSet = [card("6S"), card("KH")].iter().copied().collect();
+
+        assert_eq!(strategy.choose_next(&hand, &table, &[]), vec![card("6S")]);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::strategy::*;
+use crate::strategy::human::*;
+use crate::strategy::computer_v1::*;
+use crate::strategy::computer_v2::*;
+use crate::strategy::heuristic::*;
+use crate::strategy::neural::*;
+use crate::strategy::baseline::*;
+use crate::strategy::cfr::*;
+
+use std::str::FromStr;
+
+/// The `name=value` pairs following the `:` in a spec such as
+/// `heuristic:pickup_weight=12,defensive_retention=3`.
+#[derive(Clone, Debug, Default)]
+pub struct Params
+{
+    values: Vec<(String, String)>
+}
+
+impl Params
+{
+    pub fn parse(s: &str) -> Result<Params, String>
+    {
+        let mut values = Vec::new();
+
+        for pair in s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match pair.split_once('=') {
+                Some((k, v)) => values.push((k.trim().to_string(), v.trim().to_string())),
+                None => return Err(format!("Expected name=value, found '{}'", pair))
+            }
+        }
+
+        Ok(Params { values })
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str>
+    {
+        self.values.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get<T: FromStr>(&self, name: &str, default: T) -> Result<T, String>
+    {
+        match self.get_str(name) {
+            Some(v) => v.parse::<T>().map_err(|_| format!("Invalid value for {}: '{}'", name, v)),
+            None => Ok(default)
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str>
+    {
+        self.values.iter().map(|(k, _)| k.as_str())
+    }
+}
+
+pub type Constructor = fn(&str, &Params) -> Result<StrategyType, String>;
+
+pub struct Registration
+{
+    pub key: &'static str,
+    pub description: &'static str,
+    pub parameters: Vec<&'static str>,
+    constructor: Constructor
+}
+
+pub struct Registry
+{
+    entries: Vec<Registration>
+}
+
+impl Registry
+{
+    /// Every strategy compiled into the crate; `register` adds more.
+    pub fn standard() -> Registry
+    {
+        let mut registry = Registry { entries: Vec::new() };
+
+        registry.register("computer_v1",
+            "Greedy: dumps the highest scoring chain, weighting penalty cards.",
+            vec![], |spec, _| Ok(ComputerStrategyV1::strategy_type(spec)));
+
+        registry.register("computer_v2",
+            "As computer_v1, additionally tracking which cards are still live.",
+            vec![], |spec, _| Ok(ComputerStrategyV2::strategy_type(spec)));
+
+        registry.register("heuristic",
+            "Greedy with tunable weights (see `q_learning evolve`).",
+            HeuristicParams::parameter_names(),
+            |spec, params| Ok(HeuristicStrategy::strategy_type(spec, HeuristicParams::from_params(params)?)));
+
+        registry.register("neural",
+            "Feed-forward network scoring each chain, loaded from `path`.",
+            vec!["path"],
+            |spec, params| match params.get_str("path") {
+                Some(path) => NeuralStrategy::strategy_type(spec, path),
+                None => Err("neural requires a path parameter".to_string())
+            });
+
+        registry.register("cfr",
+            "Two-player CFR policy over abstract moves, loaded from `path` (see `q_learning cfr`).",
+            vec!["path"],
+            |spec, params| match params.get_str("path") {
+                Some(path) => CfrStrategy::strategy_type(spec, path),
+                None => Err("cfr requires a path parameter".to_string())
+            });
+
+        registry.register("random",
+            "Plays a uniformly random legal chain.",
+            vec![], |spec, _| Ok(RandomStrategy::strategy_type(spec)));
+
+        registry.register("longest_chain",
+            "Plays as many cards as possible.",
+            vec![], |spec, _| Ok(RuleOfThumbStrategy::strategy_type(spec, "Longest chain", longest_chain_score)));
+
+        registry.register("dump_highest",
+            "Plays the single highest scoring card.",
+            vec![], |spec, _| Ok(RuleOfThumbStrategy::strategy_type(spec, "Dump highest", dump_highest_score)));
+
+        registry.register("defender",
+            "Always passes penalties on; otherwise keeps penalty cards back.",
+            vec![], |spec, _| Ok(RuleOfThumbStrategy::strategy_type(spec, "Defender", defender_score)));
+
+        registry.register("ace_hoarder",
+            "As computer_v1, but only plays a wild card (an ace) when nothing else goes.",
+            vec![], |spec, _| Ok(RuleOfThumbStrategy::strategy_type(spec, "Ace hoarder", ace_hoarder_score)));
+
+        registry.register("human",
+            "Prompts on stdin; `hint` lists the chains the `advisor` bot (default computer_v1) would play.",
+            vec!["advisor"], |spec, params| {
+                let advisor = Registry::standard().parse(params.get_str("advisor").unwrap_or("computer_v1"))?;
+                HumanStrategy::strategy_type(spec, advisor)
+            });
+
+        registry
+    }
+
+    pub fn register(&mut self, key: &'static str, description: &'static str,
+                    parameters: Vec<&'static str>, constructor: Constructor)
+    {
+        self.entries.retain(|r| r.key != key);
+        self.entries.push(Registration { key, description, parameters, constructor });
+    }
+
+    pub fn entries(&self) -> &[Registration]
+    {
+        &self.entries
+    }
+
+    /// Builds a strategy from a spec of the form `key[:name=value,...]`.
+    pub fn parse(&self, spec: &str) -> Result<StrategyType, String>
+    {
+        let spec = spec.trim();
+
+        let (key, params) = match spec.split_once(':') {
+            Some((k, p)) => (k.trim(), Params::parse(p)?),
+            None => (spec, Params::default())
+        };
+
+        let registration = self.entries
+                            .iter()
+                            .find(|r| r.key == key)
+                            .ok_or(format!("Unknown strategy '{}'", key))?;
+
+        if let Some(unknown) = params.names().find(|n| !registration.parameters.contains(n)) {
+            return Err(format!("{} has no parameter '{}'", key, unknown));
+        }
+
+        (registration.constructor)(spec, &params)
+    }
+
+    /// Reads one spec per line, ignoring blank lines and `#` comments.
+    pub fn parse_file(&self, path: &str) -> Result<Vec<StrategyType>, String>
+    {
+        let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {}: {}", path, e))?;
+
+        text.lines()
+            .map(|l| l.split('#').next().unwrap().trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| self.parse(l))
+            .collect()
+    }
+
+    pub fn describe(&self) -> String
+    {
+        let mut s = String::new();
+
+        for r in &self.entries {
+            s += &format!("{:<12} {}\n", r.key, r.description);
+            if !r.parameters.is_empty() {
+                s += &format!("{:<12} parameters: {}\n", "", r.parameters.join(", "));
+            }
+        }
+
+        s
+    }
+}
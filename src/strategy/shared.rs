@@ -1,64 +1,448 @@
 use crate::cards::*;
-use crate::game::*;
-use crate::blackjack::*;
+use crate::rules::*;
 
 use std::collections::HashSet;
+use std::hash::{BuildHasherDefault,Hasher};
 
-const MAX_PERMUTATIONS : i32 = 10_000;
+/// Multiplicative hash for the search states below; they are already
+/// well distributed so SipHash is wasted effort.
+#[derive(Default)]
+struct StateHasher(u64);
 
-pub fn iterate_valid_chains<T>(log: &[Turn], scratch: &mut [Card], ctr: &mut i32, action: &mut T)
+impl Hasher for StateHasher {
+    fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes: &[u8])
+    {
+        for b in bytes { self.write_u64(*b as u64); }
+    }
+
+    fn write_u64(&mut self, n: u64)
+    {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_usize(&mut self, n: usize) { self.write_u64(n as u64); }
+}
+
+/// Most search states `iterate_valid_chains` expands for one hand. Hands of
+/// up to 25 cards stay well below this, but some of 30 or more, mostly from
+/// two decks with jokers, link in more ways than fit in memory.
+pub const MAX_SEARCH_STATES : usize = 1 << 20;
+
+/// Whether `iterate_valid_chains` visited every distinct chain, or stopped
+/// at `MAX_SEARCH_STATES`.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enumeration
+{
+    Complete,
+    Truncated
+}
+
+/// Calls `action` once for every distinct chain that can be played from
+/// `hand`, where chains are distinct if they leave a different hand, a
+/// different top card, or a different penalty or number of skips to pass
+/// on; swapping identical copies of a card never makes a chain distinct.
+/// The search expands each (cards left, possible ends, penalty, skips)
+/// state once. The possible ends are the last card, or after a joker every
+/// card it could be standing in for.
+///
+/// Every card that can start a chain is offered on its own first, whatever
+/// the limit, so a search cut short at `MAX_SEARCH_STATES` still offers
+/// each of them; it misses longer chains and says so by returning
+/// `Truncated`. No limit would do for every hand, as the number of distinct
+/// chains grows exponentially with its size, so callers must handle that.
+pub fn iterate_valid_chains<T>(table: &TableState, hand: &CardSet, action: &mut T) -> Enumeration
+where
+    T : for<'a> FnMut(&'a [Card])
+{
+    enumerate_chains(table, hand, MAX_SEARCH_STATES, action)
+}
+
+fn enumerate_chains<T>(table: &TableState, hand: &CardSet, limit: usize, action: &mut T) -> Enumeration
 where
     T : for<'a> FnMut(&'a [Card])
 {
-    fn iterate_extensions<T>(chain_length: usize, cards: &[Card], ctr: &mut i32, action: &mut T) 
+    type State = (CardSet, CardSet, usize, usize);
+
+    struct Search<'a, T>
+    {
+        rules: &'a Rules,
+        limit: usize,
+        truncated: bool,
+        seen: HashSet<State, BuildHasherDefault<StateHasher>>,
+        chain: Vec<Card>,
+        action: &'a mut T
+    }
+
+    /// Records the state the chain has reached, returning whether it is new.
+    fn visit<T>(s: &mut Search<T>, state: State) -> bool
+    where
+        T : for<'a> FnMut(&'a [Card])
+    {
+        if s.seen.len() >= s.limit {
+            s.truncated |= !s.seen.contains(&state);
+            return false;
+        }
+        if !s.seen.insert(state) { return false; }
+
+        (s.action)(&s.chain);
+        true
+    }
+
+    fn expand<T>(s: &mut Search<T>, (remaining, ends, penalty, skips): State)
     where
         T : for<'a> FnMut(&'a [Card])
     {
-        action(&cards[0..chain_length]);
-        
-        *ctr += 1; if *ctr > MAX_PERMUTATIONS { return; }
+        let follows = ends.iter().fold(CardSet::new(), |set, end| set | s.rules.links(end));
 
-        let prev = cards[chain_length-1];
+        for next in &(follows & remaining) {
 
-        let mut scratch : Vec<Card> = cards.iter().cloned().collect();
+            let mut next_remaining = remaining;
+            next_remaining.remove(&next);
 
-        for i in chain_length..cards.len()
-        {
-            if !can_link(prev, cards[i]) { continue; }
-            if i != chain_length { scratch.swap(chain_length, i); }
+            let next_ends = if next.is_joker() { follows & CardSet::full() } else { single(next) };
+            let next_penalty = match s.rules.penalty_value(next) { 0 => 0, p => penalty + p };
+            let next_state = (next_remaining, next_ends, next_penalty, trailing_skips(s.rules, skips, next));
 
-            iterate_extensions(chain_length + 1, &scratch, ctr, action);
+            s.chain.push(next);
+            if visit(s, next_state) { expand(s, next_state); }
+            s.chain.pop();
         }
     }
-    
-    for f in 0..scratch.len() {
-        
-        if !can_follow(log, scratch[f]) { continue; }
-        if f != 0 { scratch.swap(0, f); }
 
-        iterate_extensions(1, &scratch, ctr, action);
+    /// The skips a chain passes on once `next` is added, counting only the
+    /// skip cards it ends with as `TableState::apply` does.
+    fn trailing_skips(rules: &Rules, skips: usize, next: Card) -> usize
+    {
+        match (rules.effect(next) == Some(Effect::Skip), rules.stacking_skips) {
+            (false, _) => 0,
+            (true, false) => 1,
+            (true, true) => skips + 1
+        }
     }
+
+    fn single(card: Card) -> CardSet
+    {
+        std::iter::once(card).collect()
+    }
+
+    let mut search = Search {
+        rules: &table.rules,
+        limit,
+        truncated: false,
+        seen: HashSet::default(),
+        chain: Vec::with_capacity(hand.len()),
+        action
+    };
+
+    let starts : Vec<(Card, State)> = hand.distinct().iter().filter(|first| can_follow(table, *first)).map(|first| {
+        let mut remaining = *hand;
+        remaining.remove(&first);
+
+        let state = (remaining, chain_ends(&table.rules, None, first), table.rules.penalty_value(first), trailing_skips(&table.rules, 0, first));
+        (first, state)
+    }).collect();
+
+    let mut fresh = Vec::with_capacity(starts.len());
+
+    for (first, state) in &starts {
+        let is_new = search.seen.insert(*state);
+        if is_new { (search.action)(&[*first]); }
+        fresh.push(is_new);
+    }
+
+    for ((first, state), fresh) in starts.iter().zip(fresh) {
+        if !fresh { continue; }
+
+        search.chain.push(*first);
+        expand(&mut search, *state);
+        search.chain.pop();
+    }
+
+    if search.truncated { Enumeration::Truncated } else { Enumeration::Complete }
 }
 
-pub fn find_best_valid<T>(log: &[Turn], hand: &HashSet<Card>, score_function: T) -> Vec<Card>
+/// The highest scoring chain, or nothing if no card goes: a chain is played
+/// however low it scores, with NaN scoring lowest of all. Should the search
+/// be cut short, the best chain it saw is grown by `extend_greedily`.
+pub fn find_best_valid<T>(table: &TableState, hand: &CardSet, score_function: T) -> Vec<Card>
 where 
-    T : for<'a> Fn(&'a [Card]) -> f32
+    T : for<'a> FnMut(&'a [Card]) -> f32
 {
-    let mut best_score : f32 = 0.0;
-    let mut best = Vec::<Card>::new();
-    let mut n_considered = 0;
+    find_best_within(table, hand, MAX_SEARCH_STATES, score_function).0
+}
 
-    let mut scratch = hand.iter().cloned().collect::<Vec<Card>>();
+fn find_best_within<T>(table: &TableState, hand: &CardSet, limit: usize, mut score_function: T) -> (Vec<Card>, Enumeration)
+where
+    T : for<'a> FnMut(&'a [Card]) -> f32
+{
+    let mut score = |chain: &[Card]| match score_function(chain) {
+        s if s.is_nan() => f32::NEG_INFINITY,
+        s => s
+    };
+
+    let mut best_score : Option<f32> = None;
+    let mut best = Vec::<Card>::new();
 
-    iterate_valid_chains(log, &mut scratch, &mut n_considered, 
-        &mut |chain: &[Card]| {
-        let this_score = score_function(chain);
-        if this_score > best_score {
-            best_score = this_score;
+    let searched = enumerate_chains(table, hand, limit, &mut |chain: &[Card]| {
+        let this_score = score(chain);
+        if best_score.is_none_or(|s| this_score > s) {
+            best_score = Some(this_score);
             best.clear();
             best.extend_from_slice(chain)
         }
     });
 
-    best
-}
\ No newline at end of file
+    match searched {
+        Enumeration::Complete => (best, searched),
+        Enumeration::Truncated => (extend_greedily(table, hand, best, score), searched)
+    }
+}
+
+/// Adds to `chain` a card at a time, each time the one that scores best,
+/// for as long as that raises its score. A search cut short misses longer
+/// chains, so this grows the best one it did see as far as it pays.
+pub fn extend_greedily<T>(table: &TableState, hand: &CardSet, mut chain: Vec<Card>, mut score_function: T) -> Vec<Card>
+where
+    T : for<'a> FnMut(&'a [Card]) -> f32
+{
+    if chain.is_empty() { return chain; }
+
+    let rules = &table.rules;
+    let mut remaining = *hand;
+    let mut ends = chain_ends(rules, None, chain[0]);
+
+    for card in &chain { remaining.remove(card); }
+    for card in &chain[1..] { ends = chain_ends(rules, Some(&ends), *card); }
+
+    let mut score = score_function(&chain);
+
+    loop {
+        let follows = ends.iter().fold(CardSet::new(), |set, end| set | rules.links(end));
+        let mut best = None;
+
+        for next in &(follows & remaining).distinct() {
+            chain.push(next);
+            let next_score = score_function(&chain);
+            chain.pop();
+
+            if next_score > score {
+                score = next_score;
+                best = Some(next);
+            }
+        }
+
+        match best {
+            Some(next) => {
+                remaining.remove(&next);
+                ends = chain_ends(rules, Some(&ends), next);
+                chain.push(next);
+            },
+            None => return chain
+        }
+    }
+}
+
+/// Whether `card` is worth holding back to pass on a penalty played at us.
+pub fn is_defensive(rules: &Rules, card: Card) -> bool
+{
+    rules.penalty_value(card) > 0
+}
+
+pub const PICK_UP_WEIGHTING : f32 = 10.0;
+
+/// The suit the hand holds most of; jokers count for nothing.
+pub fn choose_suit(hand: &CardSet) -> Suit
+{
+    *Suit::all()
+        .iter()
+        .max_by_key(|s| hand.iter().filter(|c| !c.is_joker() && c.suit() == **s).count())
+        .unwrap()
+}
+
+pub fn score(table: &TableState, chain: &[Card]) -> f32
+{
+    let mut score = chain.iter().map(|c| table.rules.card_score(*c)).sum::<i32>() as f32;
+
+    for card in chain {
+        score += table.rules.penalty_value(*card) as f32 * PICK_UP_WEIGHTING;
+    }
+
+    if table.penalty > 0 {
+        score += table.penalty as f32 * PICK_UP_WEIGHTING;
+    }
+
+    score
+}
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::game::{Action, Turn};
+
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    type Outcome = (CardSet, CardSet, usize, usize);
+
+    /// What playing `chain` leaves behind: the hand, the cards it could be
+    /// ending on, and the penalty and skips passed on.
+    fn outcome(table: &TableState, hand: &CardSet, chain: &[Card]) -> Outcome
+    {
+        let mut remaining = *hand;
+        for card in chain { assert!(remaining.remove(card), "{:?} is not in {:?}", card, hand); }
+
+        let mut ends = chain_ends(&table.rules, None, chain[0]);
+        for next in &chain[1..] { ends = chain_ends(&table.rules, Some(&ends), *next); }
+
+        let mut after = table.clone();
+        after.apply(&Turn { player: None, action: Action::Played(chain.to_vec()) });
+
+        (remaining, ends, after.penalty, after.skips)
+    }
+
+    /// Every outcome reachable by trying each ordering of each subset of `cards`.
+    fn brute_force(table: &TableState, hand: &CardSet, cards: &[Card], chain: &mut Vec<Card>, used: &mut Vec<bool>, found: &mut HashSet<Outcome>)
+    {
+        if !chain.is_empty() {
+            if !is_valid(table, chain) { return; }
+            found.insert(outcome(table, hand, chain));
+        }
+
+        for i in 0..cards.len() {
+            if used[i] { continue; }
+
+            used[i] = true;
+            chain.push(cards[i]);
+            brute_force(table, hand, cards, chain, used, found);
+            chain.pop();
+            used[i] = false;
+        }
+    }
+
+    fn random_table(rules: &Arc<Rules>, rng: &mut StdRng, hand_size: usize) -> (TableState, CardSet)
+    {
+        let mut deck : Vec<Card> = rules.deck(2).iter().collect();
+        deck.shuffle(rng);
+
+        let mut table = TableState::new(rules.clone(), deck[0]);
+        if rng.gen_bool(0.2) { table.active = false; }
+        if rng.gen_bool(0.2) { table.nominated = Some(*Suit::all().choose(rng).unwrap()); }
+
+        (table, deck[1..=hand_size].iter().cloned().collect())
+    }
+
+    fn rule_sets() -> Vec<Arc<Rules>>
+    {
+        let mut jokers = Rules::default();
+        jokers.jokers_per_deck = 2;
+        jokers.joker_penalty = 3;
+
+        let mut stacking = jokers.clone();
+        stacking.stacking_skips = true;
+
+        vec![Arc::new(Rules::default()), Arc::new(jokers), Arc::new(stacking)]
+    }
+
+    #[test]
+    fn finds_every_outcome_once()
+    {
+        let mut rng = StdRng::seed_from_u64(31);
+
+        for rules in rule_sets() {
+            for _ in 0..300 {
+                let hand_size = rng.gen_range(1..=7);
+                let (table, hand) = random_table(&rules, &mut rng, hand_size);
+
+                let mut enumerated = HashSet::new();
+                let searched = iterate_valid_chains(&table, &hand, &mut |chain: &[Card]| {
+                    assert!(is_valid(&table, chain), "{:?} is not valid on {:?}", chain, table.top_card);
+                    assert!(enumerated.insert(outcome(&table, &hand, chain)), "{:?} repeats an outcome", chain);
+                });
+
+                let cards : Vec<Card> = hand.iter().collect();
+                let mut expected = HashSet::new();
+                brute_force(&table, &hand, &cards, &mut Vec::new(), &mut vec![false; cards.len()], &mut expected);
+
+                assert_eq!(searched, Enumeration::Complete);
+                assert_eq!(enumerated, expected, "hand {:?} on {:?}", hand, table.top_card);
+            }
+        }
+    }
+
+    #[test]
+    fn stacked_skips_are_distinct()
+    {
+        let mut rules = Rules::default();
+        rules.stacking_skips = true;
+
+        let table = TableState::new(Arc::new(rules), Card::try_from("8H").unwrap());
+        let hand : CardSet = ["8S", "8D", "9D"].iter().map(|c| Card::try_from(*c).unwrap()).collect();
+
+        let mut skips = Vec::new();
+        let searched = iterate_valid_chains(&table, &hand, &mut |chain: &[Card]| {
+            if chain.last() == Some(&Card::try_from("8D").unwrap()) { skips.push(chain.len()); }
+        });
+        skips.sort_unstable();
+
+        assert_eq!(searched, Enumeration::Complete);
+
+        // 8D alone, and after 8S where it passes on two skips.
+        assert_eq!(skips, vec![1, 2]);
+    }
+
+    #[test]
+    fn truncated_search_offers_every_first_card()
+    {
+        let mut rng = StdRng::seed_from_u64(32);
+        let rules = &rule_sets()[1];
+        let mut truncated = 0;
+
+        for _ in 0..50 {
+            let (table, hand) = random_table(rules, &mut rng, 20);
+
+            let mut singles = CardSet::new();
+            let searched = enumerate_chains(&table, &hand, 8, &mut |chain: &[Card]| {
+                if chain.len() == 1 { singles.insert(chain[0]); }
+            });
+
+            let going : CardSet = hand.distinct().iter().filter(|c| can_follow(&table, *c)).collect();
+
+            assert_eq!(singles, going);
+            if searched == Enumeration::Truncated { truncated += 1; }
+        }
+
+        assert!(truncated > 25, "only {} of 50 searches stopped early", truncated);
+    }
+
+    #[test]
+    fn the_best_chain_of_a_truncated_search_is_grown()
+    {
+        let rules = Arc::new(Rules::default());
+
+        // A whole deck links in far more ways than the search can hold.
+        let hand = rules.deck(1);
+        let table = TableState::new(rules.clone(), Card::try_from("5S").unwrap());
+
+        let (chain, searched) = find_best_within(&table, &hand, MAX_SEARCH_STATES, |chain| score(&table, chain));
+        assert_eq!(searched, Enumeration::Truncated);
+        assert!(is_valid(&table, &chain));
+
+        // Every card adds to the score, so the chain must not be able to grow.
+
+        let mut remaining = hand;
+        for card in &chain { assert!(remaining.remove(card)); }
+
+        for next in &remaining.distinct() {
+            let mut longer = chain.clone();
+            longer.push(next);
+            assert!(!is_valid(&table, &longer), "{:?} could still be added to {:?}", next, chain);
+        }
+    }
+}
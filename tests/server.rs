@@ -0,0 +1,125 @@
+use blackjack::rules::Rules;
+use blackjack::server::Server;
+use blackjack::strategy::registry::Registry;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+struct Player
+{
+    reader: BufReader<TcpStream>,
+    writer: TcpStream
+}
+
+impl Player
+{
+    fn connect(address: SocketAddr, name: &str) -> Player
+    {
+        let writer = TcpStream::connect(address).unwrap();
+        writer.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+        writer.set_nodelay(true).unwrap();
+
+        let mut player = Player { reader: BufReader::new(writer.try_clone().unwrap()), writer };
+
+        assert!(player.read().starts_with("WELCOME guest"));
+        player.send(&format!("NAME {}", name));
+        assert_eq!(player.read(), format!("WELCOME {}", name));
+        player
+    }
+
+    fn send(&mut self, line: &str)
+    {
+        writeln!(self.writer, "{}", line).unwrap();
+    }
+
+    fn read(&mut self) -> String
+    {
+        let mut line = String::new();
+        assert!(self.reader.read_line(&mut line).unwrap() > 0, "the server hung up");
+        line.trim_end().to_string()
+    }
+
+    /// Plays out a hand, trying each card in hand on its own and then
+    /// picking up, and returns everything the server sent.
+    fn play(mut self) -> Vec<String>
+    {
+        let mut lines = Vec::new();
+        let mut hand : Vec<String> = Vec::new();
+        let mut tries : Vec<String> = Vec::new();
+
+        loop {
+            let line = self.read();
+            lines.push(line.clone());
+
+            let words : Vec<&str> = line.split(' ').collect();
+
+            match words[0] {
+                "HAND" => hand = words[1..].iter().map(|w| w.to_string()).collect(),
+                "GO" if words[1] == "SUIT" => self.send("SUIT S"),
+                "GO" | "INVALID" => {
+                    if words[0] == "GO" {
+                        tries = hand.iter().map(|c| format!("PLAY {}", c)).collect();
+                        tries.push("PLAY".to_string());
+                        tries.reverse();
+                    }
+
+                    // Should nothing be accepted, the bot moves once time is up.
+                    if let Some(attempt) = tries.pop() { self.send(&attempt); }
+                },
+                "RESULT" => return lines,
+                _ => ()
+            }
+        }
+    }
+}
+
+/// The cards after the first `skip` words of a line, sorted.
+fn cards(line: &str, skip: usize) -> Vec<String>
+{
+    let mut cards : Vec<String> = line.split(' ').skip(skip).map(|c| c.to_string()).collect();
+    cards.sort();
+    cards
+}
+
+#[test]
+fn two_clients_play_a_hand()
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let mut server = Server::new(Registry::standard().parse("computer_v1").unwrap(), Rules::default());
+    server.set_move_timeout(Duration::from_secs(1));
+
+    std::thread::spawn(move || server.serve(listener, |e| panic!("{}", e)));
+
+    let mut alice = Player::connect(address, "alice");
+    alice.send("HOST t 2");
+    assert_eq!(alice.read(), "SEATED t 0 alice");
+
+    let mut bob = Player::connect(address, "bob");
+    bob.send("JOIN t");
+    assert_eq!(bob.read(), "SEATED t 1 bob");
+    assert_eq!(alice.read(), "SEATED t 1 bob");
+
+    alice.send("START");
+
+    let bob = std::thread::spawn(move || bob.play());
+    let seen = [alice.play(), bob.join().unwrap()];
+
+    for (seat, lines) in seen.iter().enumerate() {
+        let seats : Vec<&String> = lines.iter().filter(|l| l.starts_with("SEAT ")).collect();
+        assert_eq!(seats, vec![&format!("SEAT {}", seat)]);
+
+        let reveals : Vec<&String> = lines.iter().filter(|l| l.starts_with("REVEAL ")).collect();
+        assert_eq!(reveals.len(), 2);
+
+        let last_hand = lines.iter().rev().find(|l| l.starts_with("HAND")).unwrap();
+        assert_eq!(cards(last_hand, 1), cards(reveals[seat], 2), "seat {} was sent someone else's hand", seat);
+
+        assert!(lines.last().unwrap().starts_with("RESULT "));
+    }
+
+    let first_hands : Vec<Vec<String>> = seen.iter().map(|lines| cards(lines.iter().find(|l| l.starts_with("HAND")).unwrap(), 1)).collect();
+    assert!(first_hands[0].iter().all(|c| !first_hands[1].contains(c)), "both seats were sent the same cards");
+}